@@ -0,0 +1,277 @@
+use std::ops::RangeInclusive;
+
+use lsp_types::{Position, Range};
+use unimarkup_core::elements::{atomic::Heading, blocks::Block, indents::BulletListEntry};
+use unimarkup_inline::Inline;
+
+use crate::line_index::LineIndex;
+
+/// Returns the lines a block spans in the document.
+///
+/// Lines start at 1 like all Unimarkup positions.
+/// Returns `None` for blocks whose position is not known.
+pub(crate) fn block_lines(block: &Block) -> Option<RangeInclusive<usize>> {
+    match block {
+        Block::Heading(heading) => {
+            let end_line = inlines_end_line(&heading.content).unwrap_or(heading.line_nr);
+            Some(heading.line_nr..=end_line.max(heading.line_nr))
+        }
+        Block::Paragraph(paragraph) => {
+            let end_line = inlines_end_line(&paragraph.content).unwrap_or(paragraph.line_nr);
+            Some(paragraph.line_nr..=end_line.max(paragraph.line_nr))
+        }
+        Block::Verbatim(verbatim) => {
+            // Content lines are enclosed by the opening and closing fence
+            let end_line = verbatim.line_nr + verbatim.content.lines().count() + 1;
+            Some(verbatim.line_nr..=end_line)
+        }
+        Block::BulletList(bullet_list) => {
+            let start_line = bullet_list.line_nr;
+            let end_line = bullet_list
+                .entries
+                .iter()
+                .map(entry_end_line)
+                .max()
+                .unwrap_or(start_line);
+            Some(start_line..=end_line.max(start_line))
+        }
+        // Unreachable as long as the core has no other blocks
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+/// Finds the column the content of the given heading starts at in its first line.
+///
+/// The content follows the level and all whitespace after it.
+/// Columns are counted in characters starting at 0.
+pub(crate) fn heading_content_column(heading: &Heading, line_index: &LineIndex) -> usize {
+    let line = line_index.line(heading.line_nr.saturating_sub(1) as u32);
+    let level_end = line.chars().take_while(|c| c.is_whitespace()).count()
+        + usize::from(u8::from(heading.level));
+
+    line.chars()
+        .skip(level_end)
+        .take_while(|c| c.is_whitespace())
+        .count()
+        + level_end
+}
+
+/// Inlines of a block, whose columns in their first line may be relative to the block content.
+pub(crate) struct BlockInlines<'a> {
+    pub(crate) inlines: &'a [Inline],
+    /// Line the inlines start at, starting at 1.
+    pub(crate) line_nr: usize,
+    /// Column the inline columns of the first line start at, counted in characters starting at 0.
+    pub(crate) first_line_offset: usize,
+}
+
+impl BlockInlines<'_> {
+    /// Converts a Unimarkup column of the inlines into a column counted in characters starting at 0.
+    pub(crate) fn char_column(&self, line_nr: usize, column: usize) -> usize {
+        let offset = if line_nr == self.line_nr {
+            self.first_line_offset
+        } else {
+            0
+        };
+
+        column.saturating_sub(1) + offset
+    }
+}
+
+/// Returns the inlines of the heading, paragraph or bullet list entry heading at the given line.
+///
+/// Lines start at 1. Blocks nested in bullet list entries are searched as well.
+pub(crate) fn inlines_at_line<'a>(
+    blocks: &'a [Block],
+    line_index: &LineIndex,
+    line_nr: usize,
+) -> Option<BlockInlines<'a>> {
+    let block = blocks
+        .iter()
+        .find(|block| block_lines(block).is_some_and(|lines| lines.contains(&line_nr)))?;
+
+    match block {
+        Block::Heading(heading) => Some(BlockInlines {
+            inlines: &heading.content,
+            line_nr: heading.line_nr,
+            first_line_offset: heading_content_column(heading, line_index),
+        }),
+        Block::Paragraph(paragraph) => Some(BlockInlines {
+            inlines: &paragraph.content,
+            line_nr: paragraph.line_nr,
+            first_line_offset: 0,
+        }),
+        Block::BulletList(bullet_list) => {
+            let entry = bullet_list
+                .entries
+                .iter()
+                .find(|entry| (entry.line_nr..=entry_end_line(entry)).contains(&line_nr))?;
+            let heading_end = inlines_end_line(&entry.heading).unwrap_or(entry.line_nr);
+
+            if (entry.line_nr..=heading_end).contains(&line_nr) {
+                Some(BlockInlines {
+                    inlines: &entry.heading,
+                    line_nr: entry.line_nr,
+                    first_line_offset: entry_columns(entry, line_index).content,
+                })
+            } else {
+                inlines_at_line(&entry.body, line_index, line_nr)
+            }
+        }
+        _ => None,
+    }
+}
+
+/// A block or bullet list entry that can be referenced by its id.
+pub(crate) struct Element<'a> {
+    pub(crate) id: &'a str,
+    /// Lines the element spans, starting at 1.
+    pub(crate) lines: RangeInclusive<usize>,
+    /// The heading, if the element is one.
+    pub(crate) heading: Option<&'a Heading>,
+}
+
+impl Element<'_> {
+    /// Returns the range of complete lines the element spans.
+    pub(crate) fn range(&self, line_index: &LineIndex) -> Range {
+        // Unimarkup lines start at 1
+        let start_line = self.lines.start().saturating_sub(1) as u32;
+        let end_line = self.lines.end().saturating_sub(1) as u32;
+
+        Range::new(Position::new(start_line, 0), line_index.line_end(end_line))
+    }
+}
+
+/// Returns all elements of the given blocks in document order,
+/// including the entries and nested blocks of bullet lists.
+///
+/// Blocks whose position is not known are skipped.
+pub(crate) fn elements(blocks: &[Block]) -> Vec<Element<'_>> {
+    let mut elements = Vec::new();
+    push_elements(&mut elements, blocks);
+    elements
+}
+
+fn push_elements<'a>(elements: &mut Vec<Element<'a>>, blocks: &'a [Block]) {
+    for block in blocks {
+        let Some(lines) = block_lines(block) else {
+            continue;
+        };
+
+        match block {
+            Block::Heading(heading) => elements.push(Element {
+                id: &heading.id,
+                lines,
+                heading: Some(heading),
+            }),
+            Block::Paragraph(paragraph) => elements.push(Element {
+                id: &paragraph.id,
+                lines,
+                heading: None,
+            }),
+            Block::Verbatim(verbatim) => elements.push(Element {
+                id: &verbatim.id,
+                lines,
+                heading: None,
+            }),
+            Block::BulletList(bullet_list) => {
+                elements.push(Element {
+                    id: &bullet_list.id,
+                    lines,
+                    heading: None,
+                });
+
+                for entry in &bullet_list.entries {
+                    elements.push(Element {
+                        id: &entry.id,
+                        lines: entry.line_nr..=entry_end_line(entry),
+                        heading: None,
+                    });
+                    push_elements(elements, &entry.body);
+                }
+            }
+            // Unreachable as long as the core has no other blocks
+            #[allow(unreachable_patterns)]
+            _ => {}
+        }
+    }
+}
+
+/// Columns of the keyword and the heading content of a bullet list entry.
+///
+/// Columns are counted in characters starting at 0.
+pub(crate) struct EntryColumns {
+    pub(crate) keyword: usize,
+    pub(crate) content: usize,
+}
+
+/// Finds the columns of the keyword and heading content of the given entry in its line.
+///
+/// Leading whitespace is skipped, so keywords of indented and nested entries are found as well.
+pub(crate) fn entry_columns(entry: &BulletListEntry, line_index: &LineIndex) -> EntryColumns {
+    let line = line_index.line(entry.line_nr.saturating_sub(1) as u32);
+    let keyword = line.chars().take_while(|c| c.is_whitespace()).count();
+
+    // The entry keyword is always one of `-`, `+` or `*`
+    let content = line
+        .chars()
+        .skip(keyword + 1)
+        .take_while(|c| c.is_whitespace())
+        .count()
+        + keyword
+        + 1;
+
+    EntryColumns { keyword, content }
+}
+
+pub(crate) fn entry_end_line(entry: &BulletListEntry) -> usize {
+    let heading_end = inlines_end_line(&entry.heading).unwrap_or(entry.line_nr);
+    let body_end = entry
+        .body
+        .iter()
+        .filter_map(|block| block_lines(block).map(|lines| *lines.end()))
+        .max()
+        .unwrap_or(entry.line_nr);
+
+    heading_end.max(body_end).max(entry.line_nr)
+}
+
+fn inlines_end_line(inlines: &[Inline]) -> Option<usize> {
+    inlines.iter().map(|inline| inline.span().end().line).max()
+}
+
+/// Returns the text of the given inlines without delimiters and attributes.
+///
+/// Line breaks and whitespace runs are collapsed into single spaces.
+pub(crate) fn inlines_text(inlines: &[Inline]) -> String {
+    let mut text = String::new();
+    push_inlines_text(&mut text, inlines);
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn push_inlines_text<'a>(text: &mut String, inlines: impl IntoIterator<Item = &'a Inline>) {
+    for inline in inlines {
+        match inline {
+            Inline::Bold(nested)
+            | Inline::Italic(nested)
+            | Inline::Underline(nested)
+            | Inline::Subscript(nested)
+            | Inline::Superscript(nested)
+            | Inline::Overline(nested)
+            | Inline::Strikethrough(nested)
+            | Inline::Highlight(nested)
+            | Inline::Quote(nested)
+            | Inline::Substitution(nested)
+            | Inline::TextGroup(nested)
+            | Inline::Parentheses(nested)
+            | Inline::Multiple(nested) => push_inlines_text(text, nested.iter()),
+            Inline::Verbatim(plain_content)
+            | Inline::Math(plain_content)
+            | Inline::Plain(plain_content) => text.push_str(plain_content.as_str()),
+            Inline::Whitespace(_) | Inline::Newline(_) | Inline::EndOfLine(_) => text.push(' '),
+            Inline::Attributes(_) => {}
+        }
+    }
+}
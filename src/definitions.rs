@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use lsp_server::{RequestId, Response};
+use lsp_types::{
+    ClientCapabilities, GotoDefinitionParams, GotoDefinitionResponse, Location, LocationLink,
+    Position, Range, Url,
+};
+use unimarkup_core::document::Document;
+use unimarkup_inline::Inline;
+
+use crate::blocks::{elements, inlines_at_line, BlockInlines};
+use crate::doc_sync::VersionedDocument;
+use crate::line_index::LineIndex;
+use crate::workspace::UM_FILE_EXTENSION;
+
+/// Characters that end an id reference, because they delimit links and attributes.
+const REFERENCE_DELIMITERS: [char; 10] = ['(', ')', '[', ']', '{', '}', '<', '>', '"', '\''];
+
+/// Answers a `textDocument/definition` request.
+///
+/// Clients with link support also get the range of the reference under the cursor.
+pub(crate) fn get_definition_response(
+    id: RequestId,
+    params: GotoDefinitionParams,
+    documents: &HashMap<Url, VersionedDocument>,
+    link_support: bool,
+) -> Response {
+    let uri = &params.text_document_position_params.text_document.uri;
+    let position = params.text_document_position_params.position;
+
+    let definitions = match documents.get(uri) {
+        Some(um_doc) => reference_at(&um_doc.doc, &um_doc.line_index, position)
+            .map(|reference| {
+                find_definitions(
+                    uri,
+                    &reference,
+                    documents
+                        .iter()
+                        .map(|(uri, um_doc)| (uri, &um_doc.doc, &um_doc.line_index)),
+                )
+            })
+            .unwrap_or_default(),
+        None => vec![],
+    };
+
+    let result = if link_support {
+        GotoDefinitionResponse::Link(definitions)
+    } else {
+        GotoDefinitionResponse::Array(
+            definitions
+                .into_iter()
+                .map(|link| Location::new(link.target_uri, link.target_selection_range))
+                .collect(),
+        )
+    };
+
+    let result = serde_json::to_value(&result).unwrap();
+    Response {
+        id,
+        result: Some(result),
+        error: None,
+    }
+}
+
+/// Returns `true` if the client supports definition links.
+pub fn definition_link_supported(capabilities: &ClientCapabilities) -> bool {
+    capabilities
+        .text_document
+        .as_ref()
+        .and_then(|text_document| text_document.definition.as_ref())
+        .and_then(|definition| definition.link_support)
+        == Some(true)
+}
+
+/// Reference to the id of an element, e.g. the `#anchor` target of a link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// Path of the referenced document relative to the referencing document.
+    ///
+    /// `None` if the reference has no path, like `#anchor`.
+    pub path: Option<String>,
+    /// The referenced element id.
+    pub id: String,
+    /// Range of the reference in the referencing document.
+    pub range: Range,
+}
+
+/// Returns the id reference at the given position.
+///
+/// Only plain text of the parsed inlines is searched, so ids inside verbatim content are ignored.
+/// References are delimited by whitespace and the delimiters of links and attributes.
+/// A link target like `other.um#anchor` refers to the element in the given document,
+/// while `#anchor` and ids inside attributes may refer to elements of all documents.
+/// Other words are no references, even if they match an element id.
+pub fn reference_at(
+    document: &Document,
+    line_index: &LineIndex,
+    position: Position,
+) -> Option<Reference> {
+    // Unimarkup lines start at 1
+    let line_nr = position.line as usize + 1;
+    let block_inlines = inlines_at_line(&document.blocks, line_index, line_nr)?;
+
+    let chars: Vec<char> = line_index.line(position.line).chars().collect();
+    let column =
+        (line_index.char_column(position.line, position.character) as usize).min(chars.len());
+    let plain_text = plain_text_at(
+        block_inlines.inlines,
+        &block_inlines,
+        line_nr,
+        column,
+        false,
+    )?;
+
+    let is_reference_char = |c: &char| !c.is_whitespace() && !REFERENCE_DELIMITERS.contains(c);
+    let plain_start = plain_text.start.min(column);
+    let plain_end = plain_text.end.clamp(column, chars.len());
+
+    let start = column
+        - chars[plain_start..column]
+            .iter()
+            .rev()
+            .take_while(|c| is_reference_char(c))
+            .count();
+    let end = column
+        + chars[column..plain_end]
+            .iter()
+            .take_while(|c| is_reference_char(c))
+            .count();
+
+    let text: String = chars[start..end].iter().collect();
+    let (path, id) = match text.split_once('#') {
+        Some((path, id)) => ((!path.is_empty()).then(|| path.to_string()), id.to_string()),
+        None if plain_text.in_attributes => (None, text),
+        None => return None,
+    };
+
+    if id.is_empty() {
+        return None;
+    }
+
+    Some(Reference {
+        path,
+        id,
+        range: Range::new(
+            Position::new(
+                position.line,
+                line_index.encoded_column(position.line, start as u32),
+            ),
+            Position::new(
+                position.line,
+                line_index.encoded_column(position.line, end as u32),
+            ),
+        ),
+    })
+}
+
+/// Plain text inline in the line of a reference.
+struct PlainText {
+    /// Column the text starts at in the line, counted in characters starting at 0.
+    start: usize,
+    /// Column after the end of the text in the line.
+    ///
+    /// `usize::MAX` if the text continues in the next line.
+    end: usize,
+    /// `true` if the text is part of attributes.
+    in_attributes: bool,
+}
+
+/// Finds the plain text inline at the given column of the given line.
+fn plain_text_at<'a>(
+    inlines: impl IntoIterator<Item = &'a Inline>,
+    block_inlines: &BlockInlines,
+    line_nr: usize,
+    column: usize,
+    in_attributes: bool,
+) -> Option<PlainText> {
+    for inline in inlines {
+        let span = inline.span();
+        if line_nr < span.start().line || span.end().line < line_nr {
+            continue;
+        }
+
+        let start = if span.start().line == line_nr {
+            block_inlines.char_column(line_nr, span.start().column)
+        } else {
+            0
+        };
+        // Span ends include the last character
+        let end = if span.end().line == line_nr {
+            block_inlines.char_column(line_nr, span.end().column) + 1
+        } else {
+            usize::MAX
+        };
+
+        if column < start || end < column {
+            continue;
+        }
+
+        let plain_text = match inline {
+            Inline::Bold(nested)
+            | Inline::Italic(nested)
+            | Inline::Underline(nested)
+            | Inline::Subscript(nested)
+            | Inline::Superscript(nested)
+            | Inline::Overline(nested)
+            | Inline::Strikethrough(nested)
+            | Inline::Highlight(nested)
+            | Inline::Quote(nested)
+            | Inline::TextGroup(nested)
+            | Inline::Substitution(nested)
+            | Inline::Parentheses(nested)
+            | Inline::Multiple(nested) => {
+                plain_text_at(nested.iter(), block_inlines, line_nr, column, in_attributes)
+            }
+            Inline::Attributes(nested) => {
+                plain_text_at(nested.iter(), block_inlines, line_nr, column, true)
+            }
+            Inline::Plain(_) => Some(PlainText {
+                start,
+                end,
+                in_attributes,
+            }),
+            Inline::Verbatim(_)
+            | Inline::Math(_)
+            | Inline::Whitespace(_)
+            | Inline::Newline(_)
+            | Inline::EndOfLine(_) => None,
+        };
+
+        // The cursor may also touch the start of the next inline
+        if plain_text.is_some() {
+            return plain_text;
+        }
+    }
+
+    None
+}
+
+/// Finds the elements that declare the referenced id.
+///
+/// References with a path only look into the referenced document.
+/// All other references look into the referencing document first, and then into all other
+/// given documents in the order of their URIs.
+pub fn find_definitions<'a>(
+    uri: &Url,
+    reference: &Reference,
+    documents: impl IntoIterator<Item = (&'a Url, &'a Document, &'a LineIndex)>,
+) -> Vec<LocationLink> {
+    let target_uris = match &reference.path {
+        Some(path) => referenced_uris(uri, path),
+        None => vec![],
+    };
+
+    let mut documents: Vec<_> = documents
+        .into_iter()
+        .filter(|(doc_uri, _, _)| reference.path.is_none() || target_uris.contains(doc_uri))
+        .collect();
+    documents.sort_by_key(|(doc_uri, _, _)| (*doc_uri != uri, *doc_uri));
+
+    let mut definitions = Vec::new();
+    for (doc_uri, document, line_index) in documents {
+        for element in elements(&document.blocks) {
+            if element.id != reference.id {
+                continue;
+            }
+
+            let target_range = element.range(line_index);
+            // The first line declares the element, e.g. the heading line
+            let declaration_line = target_range.start.line;
+
+            definitions.push(LocationLink {
+                origin_selection_range: Some(reference.range),
+                target_uri: doc_uri.clone(),
+                target_range,
+                target_selection_range: Range::new(
+                    target_range.start,
+                    line_index.line_end(declaration_line),
+                ),
+            });
+        }
+
+        // Definitions in the referencing document shadow all other documents
+        if !definitions.is_empty() && doc_uri == uri {
+            break;
+        }
+    }
+
+    definitions
+}
+
+/// Returns the URIs the path of a reference may refer to.
+///
+/// Paths without file extension may leave out the Unimarkup extension.
+fn referenced_uris(uri: &Url, path: &str) -> Vec<Url> {
+    let mut uris: Vec<Url> = uri.join(path).into_iter().collect();
+
+    if Path::new(path).extension().is_none() {
+        uris.extend(uri.join(&format!("{}.{}", path, UM_FILE_EXTENSION)));
+    }
+
+    uris
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use lsp_server::{RequestId, Response};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DocumentDiagnosticParams, DocumentDiagnosticReport,
+    DocumentDiagnosticReportResult, FullDocumentDiagnosticReport, Position, Range,
+    RelatedFullDocumentDiagnosticReport, RelatedUnchangedDocumentDiagnosticReport,
+    UnchangedDocumentDiagnosticReport, Url,
+};
+
+use crate::line_index::LineIndex;
+
+/// Source shown for all diagnostics of this server.
+const DIAGNOSTIC_SOURCE: &str = "unimarkup";
+
+/// Diagnostics of one document together with the result id they are reported with.
+#[derive(Debug, Default, Clone)]
+pub struct DocDiagnostics {
+    /// Changes whenever the diagnostics of the document change.
+    pub result_id: String,
+    pub items: Vec<Diagnostic>,
+}
+
+/// Diagnostics of all documents that are opened by the client.
+#[derive(Debug, Default)]
+pub struct DiagnosticsStore {
+    diagnostics: HashMap<Url, DocDiagnostics>,
+    /// Counter to create unique result ids.
+    result_cnt: usize,
+}
+
+impl DiagnosticsStore {
+    pub fn get(&self, uri: &Url) -> Option<&DocDiagnostics> {
+        self.diagnostics.get(uri)
+    }
+
+    /// Stores the diagnostics of a document with a new result id.
+    ///
+    /// Returns `false` without touching the store if the diagnostics did not change.
+    pub fn update(&mut self, uri: Url, items: Vec<Diagnostic>) -> bool {
+        let unchanged = match self.diagnostics.get(&uri) {
+            Some(stored) => stored.items == items,
+            None => items.is_empty(),
+        };
+
+        if unchanged {
+            return false;
+        }
+
+        self.result_cnt += 1;
+        self.diagnostics.insert(
+            uri,
+            DocDiagnostics {
+                result_id: self.result_cnt.to_string(),
+                items,
+            },
+        );
+
+        true
+    }
+
+    /// Removes the diagnostics of a closed document.
+    ///
+    /// Returns `true` if the document had diagnostics.
+    pub fn remove(&mut self, uri: &Url) -> bool {
+        self.diagnostics.remove(uri).is_some()
+    }
+}
+
+/// Answers a `textDocument/diagnostic` request.
+pub(crate) fn get_diagnostics_response(
+    id: RequestId,
+    params: DocumentDiagnosticParams,
+    diagnostics: Option<&DocDiagnostics>,
+) -> Response {
+    let report = get_diagnostic_report(params.previous_result_id.as_deref(), diagnostics);
+
+    let result = DocumentDiagnosticReportResult::Report(report);
+    let result = serde_json::to_value(&result).unwrap();
+    Response {
+        id,
+        result: Some(result),
+        error: None,
+    }
+}
+
+/// Creates the diagnostic report of a document.
+///
+/// Reports are `unchanged` if the client already has the diagnostics of the given result id.
+pub fn get_diagnostic_report(
+    previous_result_id: Option<&str>,
+    diagnostics: Option<&DocDiagnostics>,
+) -> DocumentDiagnosticReport {
+    match diagnostics {
+        Some(diagnostics) if previous_result_id == Some(diagnostics.result_id.as_str()) => {
+            DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+                related_documents: None,
+                unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                    result_id: diagnostics.result_id.clone(),
+                },
+            })
+        }
+        Some(diagnostics) => DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+            related_documents: None,
+            full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                result_id: Some(diagnostics.result_id.clone()),
+                items: diagnostics.items.clone(),
+            },
+        }),
+        None => DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport::default()),
+    }
+}
+
+/// Converts an error returned by `unimarkup_core::unimarkup::compile` into diagnostics.
+///
+/// Core errors do not carry the position they occurred at,
+/// so the diagnostic spans the whole document starting at line 0.
+/// The message is never searched for positions, because it may mention lines of anything.
+pub fn compile_error_diagnostics(error: &impl Display, line_index: &LineIndex) -> Vec<Diagnostic> {
+    let last_line = line_index.line_count().saturating_sub(1);
+
+    vec![Diagnostic {
+        range: Range::new(Position::new(0, 0), line_index.line_end(last_line)),
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some(DIAGNOSTIC_SOURCE.to_string()),
+        message: error.to_string(),
+        ..Default::default()
+    }]
+}
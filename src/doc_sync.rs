@@ -1,66 +1,445 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+
+use lsp_types::Diagnostic;
 use lsp_types::DidChangeTextDocumentParams;
+use lsp_types::DidCloseTextDocumentParams;
 use lsp_types::DidOpenTextDocumentParams;
-use tokio::sync::mpsc::Receiver;
+use lsp_types::TextDocumentContentChangeEvent;
+use lsp_types::Url;
+use tokio::sync::mpsc;
 use tokio::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::time::Instant;
 use unimarkup_core::config::Config;
-use unimarkup_core::config::OutputFormat;
 use unimarkup_core::document::Document;
 
-pub(crate) struct DocChangeWorker;
+use crate::diagnostics::compile_error_diagnostics;
+use crate::line_index::{offset_at, LineIndex, PositionEncoding};
+use crate::settings::Settings;
+
+/// Text document notifications forwarded to the [`DocChangeWorker`].
+///
+/// All notifications are sent over one channel to keep the order the client sent them in.
+#[derive(Debug)]
+pub enum DocEvent {
+    Open(DidOpenTextDocumentParams),
+    Change(DidChangeTextDocumentParams),
+    Close(DidCloseTextDocumentParams),
+    /// The client changed the settings of the server.
+    Reconfigure(Settings),
+    /// The Unimarkup file on disk should be compiled into the workspace index.
+    Index(Url),
+    /// The Unimarkup file was deleted from disk.
+    Remove(Url),
+}
+
+/// A compiled Unimarkup document tagged with the version of the text it was compiled from.
+pub struct VersionedDocument {
+    pub uri: Url,
+    /// The LSP document version of the compiled text.
+    ///
+    /// `None` for documents that are not opened by the client,
+    /// but compiled from disk to index the workspace.
+    pub version: Option<i32>,
+    pub doc: Document,
+    /// Index of the compiled text to convert Unimarkup positions into LSP positions.
+    pub line_index: LineIndex,
+}
+
+/// Stores a compiled document, unless the store already holds a newer result of it.
+///
+/// Opened documents take precedence over their content on disk,
+/// so an indexed document never replaces an opened one.
+/// Returns `true` if the document was stored.
+pub fn store_document(
+    documents: &mut HashMap<Url, VersionedDocument>,
+    um: VersionedDocument,
+) -> bool {
+    if let Some(stored_doc) = documents.get(&um.uri) {
+        let outdated = match (stored_doc.version, um.version) {
+            (Some(_), None) => true,
+            // Results compiled from an older version than the stored one are outdated
+            (Some(stored_version), Some(version)) => stored_version > version,
+            (None, _) => false,
+        };
+
+        if outdated {
+            return false;
+        }
+    }
+
+    documents.insert(um.uri.clone(), um);
+    true
+}
+
+/// Removes an indexed document from the store.
+///
+/// Opened documents are kept, because their content does not depend on the file on disk.
+/// Returns `true` if the document was removed.
+pub fn remove_indexed_document(documents: &mut HashMap<Url, VersionedDocument>, uri: &Url) -> bool {
+    if matches!(documents.get(uri), Some(stored_doc) if stored_doc.version.is_none()) {
+        documents.remove(uri);
+        return true;
+    }
+
+    false
+}
+
+/// Updates sent from the [`DocChangeWorker`] to the document store.
+pub enum DocUpdate {
+    /// A document was compiled successfully.
+    Compiled(Box<VersionedDocument>),
+    /// Compiling a document failed.
+    Failed {
+        uri: Url,
+        version: Option<i32>,
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// A document was closed by the client.
+    Closed(Url),
+    /// An indexed document was deleted from disk.
+    Removed(Url),
+}
+
+/// Text of a document that is opened by the client.
+struct OpenDocument {
+    version: i32,
+    text: String,
+}
+
+/// Keeps the text of all opened documents in sync and compiles them.
+///
+/// Compilation is scheduled per document with a debounce window,
+/// so a burst of changes only results in one compilation of the latest text.
+pub struct DocChangeWorker {
+    tx_um: Sender<DocUpdate>,
+    tx_compiled: UnboundedSender<DocUpdate>,
+    config: Config,
+    debounce: Duration,
+    /// Position encoding negotiated with the client.
+    encoding: PositionEncoding,
+    open_docs: HashMap<Url, OpenDocument>,
+    /// Documents waiting to be compiled, mapped to the instant their debounce window ends.
+    scheduled: HashMap<Url, Instant>,
+    /// Files on disk waiting to be compiled into the workspace index.
+    index_queue: VecDeque<Url>,
+    /// Files of the index queue, to not queue a file twice.
+    queued_docs: HashSet<Url>,
+    /// All files on disk that belong to the workspace index.
+    indexed_docs: HashSet<Url>,
+    /// `true` while a file of the index queue is compiled.
+    ///
+    /// Indexed files are compiled one after another to not
+    /// take resources from compiling opened documents.
+    indexing: bool,
+}
 
 impl DocChangeWorker {
-    pub(crate) fn init(
-        tx_um: Sender<Document>,
-        rx_doc_open: Receiver<DidOpenTextDocumentParams>,
-        rx_doc_change: Receiver<DidChangeTextDocumentParams>,
+    pub fn init(
+        tx_um: Sender<DocUpdate>,
+        rx_doc_event: UnboundedReceiver<DocEvent>,
+        settings: &Settings,
+        encoding: PositionEncoding,
     ) {
-        let config = Config {
-            out_formats: Some(vec![OutputFormat::Html]),
-            ..Default::default()
+        let config = settings.to_config();
+        let debounce = settings.compile_debounce();
+
+        let (tx_compiled, rx_compiled) = mpsc::unbounded_channel::<DocUpdate>();
+
+        let worker = DocChangeWorker {
+            tx_um,
+            tx_compiled,
+            config,
+            debounce,
+            encoding,
+            open_docs: HashMap::new(),
+            scheduled: HashMap::new(),
+            index_queue: VecDeque::new(),
+            queued_docs: HashSet::new(),
+            indexed_docs: HashSet::new(),
+            indexing: false,
         };
 
-        let tx_um_clone = tx_um.clone();
-        let config_clone = config.clone();
-        tokio::spawn(
-            async move { Self::doc_open_loop(tx_um_clone, rx_doc_open, config_clone).await },
-        );
-        tokio::spawn(async move { Self::doc_change_loop(tx_um, rx_doc_change, config).await });
+        tokio::spawn(async move { worker.doc_loop(rx_doc_event, rx_compiled).await });
     }
 
-    async fn doc_open_loop(
-        tx_um: Sender<Document>,
-        mut rx_doc_open: Receiver<DidOpenTextDocumentParams>,
-        mut config: Config,
+    /// Handles document events, starts scheduled compilations
+    /// and forwards finished compilations to the document store.
+    ///
+    /// All events are handled in one loop, because incremental changes
+    /// must be applied in order on top of the text received with `didOpen`.
+    async fn doc_loop(
+        mut self,
+        mut rx_doc_event: UnboundedReceiver<DocEvent>,
+        mut rx_compiled: UnboundedReceiver<DocUpdate>,
     ) {
         loop {
-            if let Some(opened_doc) = rx_doc_open.recv().await {
-                config.um_file = opened_doc.text_document.uri.to_file_path().unwrap();
-
-                if let Ok(rendered_doc) = unimarkup_core::unimarkup::compile(
-                    &opened_doc.text_document.text.clone(),
-                    config.clone(),
-                ) {
-                    let _ = tx_um.send(rendered_doc).await;
+            let next_compile = self.scheduled.values().min().copied();
+
+            tokio::select! {
+                event = rx_doc_event.recv() => match event {
+                    Some(event) => self.handle_event(event).await,
+                    None => break,
+                },
+                Some(compile_result) = rx_compiled.recv() => {
+                    self.forward_compiled(compile_result).await;
+                }
+                _ = tokio::time::sleep_until(next_compile.unwrap_or_else(Instant::now)),
+                    if next_compile.is_some() => {
+                    self.compile_due();
                 }
             }
         }
     }
 
-    async fn doc_change_loop(
-        tx_um: Sender<Document>,
-        mut rx_doc_change: Receiver<DidChangeTextDocumentParams>,
-        mut config: Config,
-    ) {
-        loop {
-            if let Some(changes) = rx_doc_change.recv().await {
-                config.um_file = changes.text_document.uri.to_file_path().unwrap();
-
-                if let Ok(rendered_doc) = unimarkup_core::unimarkup::compile(
-                    &changes.content_changes[0].text.clone(),
-                    config.clone(),
-                ) {
-                    let _ = tx_um.send(rendered_doc).await;
+    async fn handle_event(&mut self, event: DocEvent) {
+        match event {
+            DocEvent::Open(opened_doc) => {
+                let uri = opened_doc.text_document.uri;
+                self.open_docs.insert(
+                    uri.clone(),
+                    OpenDocument {
+                        version: opened_doc.text_document.version,
+                        text: opened_doc.text_document.text,
+                    },
+                );
+
+                // Opened documents are shown without delay
+                self.scheduled.insert(uri, Instant::now());
+            }
+            DocEvent::Change(changes) => {
+                let uri = changes.text_document.uri;
+                if let Some(open_doc) = self.open_docs.get_mut(&uri) {
+                    apply_content_changes(
+                        &mut open_doc.text,
+                        &changes.content_changes,
+                        self.encoding,
+                    );
+                    open_doc.version = changes.text_document.version;
+
+                    self.scheduled.insert(uri, Instant::now() + self.debounce);
+                }
+            }
+            DocEvent::Close(closed_doc) => {
+                let uri = closed_doc.text_document.uri;
+                self.open_docs.remove(&uri);
+                self.scheduled.remove(&uri);
+
+                let _ = self.tx_um.send(DocUpdate::Closed(uri)).await;
+            }
+            DocEvent::Reconfigure(settings) => {
+                self.config = settings.to_config();
+                self.debounce = settings.compile_debounce();
+
+                // Recompile all opened and indexed documents with the new config
+                let now = Instant::now();
+                for uri in self.open_docs.keys() {
+                    self.scheduled.insert(uri.clone(), now);
                 }
+
+                for uri in self.indexed_docs.clone() {
+                    self.queue_index(uri);
+                }
+
+                self.index_next();
+            }
+            DocEvent::Index(uri) => {
+                self.indexed_docs.insert(uri.clone());
+                self.queue_index(uri);
+
+                self.index_next();
+            }
+            DocEvent::Remove(uri) => {
+                self.indexed_docs.remove(&uri);
+                if self.queued_docs.remove(&uri) {
+                    self.index_queue.retain(|queued_uri| queued_uri != &uri);
+                }
+
+                if !self.open_docs.contains_key(&uri) {
+                    let _ = self.tx_um.send(DocUpdate::Removed(uri)).await;
+                }
+            }
+        }
+    }
+
+    /// Starts compilation of all documents whose debounce window has ended.
+    ///
+    /// Compilation runs on a blocking thread and only takes the latest text of a document,
+    /// so intermediate versions that changed within the debounce window are never compiled.
+    fn compile_due(&mut self) {
+        let now = Instant::now();
+        let due_docs: Vec<Url> = self
+            .scheduled
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(uri, _)| uri.clone())
+            .collect();
+
+        for uri in due_docs {
+            self.scheduled.remove(&uri);
+
+            let Some(open_doc) = self.open_docs.get(&uri) else {
+                continue;
+            };
+
+            let text = open_doc.text.clone();
+            let version = open_doc.version;
+            let config = self.doc_config(&uri);
+            let encoding = self.encoding;
+            let tx_compiled = self.tx_compiled.clone();
+
+            tokio::task::spawn_blocking(move || {
+                let compile_result = compile(uri, Some(version), &text, config, encoding);
+                let _ = tx_compiled.send(compile_result);
+            });
+        }
+    }
+
+    /// Adds a file to the end of the index queue, unless it is already queued.
+    fn queue_index(&mut self, uri: Url) {
+        if self.queued_docs.insert(uri.clone()) {
+            self.index_queue.push_back(uri);
+        }
+    }
+
+    /// Starts compiling the next file of the index queue, unless indexing is already running.
+    ///
+    /// Files that are opened by the client are skipped, because their content on disk is outdated.
+    fn index_next(&mut self) {
+        if self.indexing {
+            return;
+        }
+
+        while let Some(uri) = self.index_queue.pop_front() {
+            self.queued_docs.remove(&uri);
+
+            if self.open_docs.contains_key(&uri) {
+                continue;
+            }
+
+            let Ok(file_path) = uri.to_file_path() else {
+                continue;
+            };
+
+            self.indexing = true;
+
+            let config = self.doc_config(&uri);
+            let encoding = self.encoding;
+            let tx_compiled = self.tx_compiled.clone();
+
+            tokio::task::spawn_blocking(move || {
+                let compile_result = match std::fs::read_to_string(file_path) {
+                    Ok(text) => compile(uri, None, &text, config, encoding),
+                    Err(_) => DocUpdate::Failed {
+                        uri,
+                        version: None,
+                        diagnostics: vec![],
+                    },
+                };
+
+                let _ = tx_compiled.send(compile_result);
+            });
+
+            return;
+        }
+    }
+
+    /// Returns the config to compile the given document with.
+    fn doc_config(&self, uri: &Url) -> Config {
+        let mut config = self.config.clone();
+
+        // Untitled buffers and documents of other schemes have no file on disk
+        if let Ok(file_path) = uri.to_file_path() {
+            config.um_file = file_path;
+        }
+
+        config
+    }
+
+    /// Forwards the result of a compilation to the document store,
+    /// unless the document was closed or changed while it was compiled.
+    ///
+    /// A changed document is compiled again once its debounce window ends,
+    /// so the store never receives a result that is older than the latest change.
+    ///
+    /// Indexed files are only forwarded if they were not opened by the client in the meantime.
+    /// A failed compilation is forwarded as well, so the outdated result of the file gets removed.
+    async fn forward_compiled(&mut self, compile_result: DocUpdate) {
+        let (uri, version) = match &compile_result {
+            DocUpdate::Compiled(compiled_doc) => (&compiled_doc.uri, compiled_doc.version),
+            DocUpdate::Failed { uri, version, .. } => (uri, *version),
+            DocUpdate::Closed(_) | DocUpdate::Removed(_) => return,
+        };
+
+        let Some(version) = version else {
+            self.indexing = false;
+
+            if !self.open_docs.contains_key(uri) {
+                let _ = self.tx_um.send(compile_result).await;
+            }
+
+            self.index_next();
+            return;
+        };
+
+        let Some(open_doc) = self.open_docs.get(uri) else {
+            return;
+        };
+
+        if version < open_doc.version {
+            return;
+        }
+
+        let _ = self.tx_um.send(compile_result).await;
+    }
+}
+
+/// Compiles the given text of a document.
+fn compile(
+    uri: Url,
+    version: Option<i32>,
+    text: &str,
+    config: Config,
+    encoding: PositionEncoding,
+) -> DocUpdate {
+    let line_index = LineIndex::new(text, encoding);
+
+    match unimarkup_core::unimarkup::compile(text, config) {
+        Ok(rendered_doc) => DocUpdate::Compiled(Box::new(VersionedDocument {
+            uri,
+            version,
+            doc: rendered_doc,
+            line_index,
+        })),
+        Err(err) => DocUpdate::Failed {
+            uri,
+            version,
+            diagnostics: compile_error_diagnostics(&err, &line_index),
+        },
+    }
+}
+
+/// Applies the given content changes in order to the text of a document.
+///
+/// Changes without a range replace the whole text.
+/// Ranged changes replace the text between the range start and end, where
+/// the range is given in code units of the negotiated position encoding.
+pub fn apply_content_changes(
+    text: &mut String,
+    changes: &[TextDocumentContentChangeEvent],
+    encoding: PositionEncoding,
+) {
+    for change in changes {
+        match change.range {
+            Some(range) => {
+                let start = offset_at(text, range.start, encoding);
+                let end = offset_at(text, range.end, encoding).max(start);
+                text.replace_range(start..end, &change.text);
+            }
+            None => {
+                *text = change.text.clone();
             }
         }
     }
@@ -0,0 +1,168 @@
+use lsp_server::{RequestId, Response};
+use lsp_types::{
+    ClientCapabilities, DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, Location,
+    Position, Range, SymbolInformation, SymbolKind, Url,
+};
+use unimarkup_core::{document::Document, elements::atomic::Heading, elements::blocks::Block};
+
+use crate::blocks::{block_lines, heading_content_column, inlines_text};
+use crate::doc_sync::VersionedDocument;
+use crate::line_index::LineIndex;
+
+/// Answers a `textDocument/documentSymbol` request.
+///
+/// Clients without support for hierarchical symbols get a flat list,
+/// where nesting is only kept as container name.
+pub(crate) fn get_document_symbols_response(
+    id: RequestId,
+    params: DocumentSymbolParams,
+    document: Option<&VersionedDocument>,
+    hierarchical_support: bool,
+) -> Response {
+    let symbols = document
+        .map(|um_doc| get_document_symbols(&um_doc.doc, &um_doc.line_index))
+        .unwrap_or_default();
+
+    let result = if hierarchical_support {
+        DocumentSymbolResponse::Nested(symbols)
+    } else {
+        DocumentSymbolResponse::Flat(flatten_symbols(&params.text_document.uri, symbols))
+    };
+
+    let result = serde_json::to_value(&result).unwrap();
+    Response {
+        id,
+        result: Some(result),
+        error: None,
+    }
+}
+
+/// Returns `true` if the client supports nested document symbols.
+pub fn hierarchical_symbols_supported(capabilities: &ClientCapabilities) -> bool {
+    capabilities
+        .text_document
+        .as_ref()
+        .and_then(|text_document| text_document.document_symbol.as_ref())
+        .and_then(|document_symbol| document_symbol.hierarchical_document_symbol_support)
+        == Some(true)
+}
+
+/// Creates one symbol per heading of the document.
+///
+/// Symbols of headings are nested inside the symbol of the previous heading with a lower level.
+/// The range of a symbol covers the section of its heading,
+/// which ends with the last block before the next heading of the same or a lower level.
+pub fn get_document_symbols(document: &Document, line_index: &LineIndex) -> Vec<DocumentSymbol> {
+    let mut root_symbols = Vec::new();
+    // Symbols of the sections that are not yet closed, with their heading level
+    let mut open_sections: Vec<(u8, DocumentSymbol)> = Vec::new();
+
+    for block in &document.blocks {
+        if let Block::Heading(heading) = block {
+            let level = u8::from(heading.level);
+
+            while matches!(open_sections.last(), Some((open_level, _)) if *open_level >= level) {
+                close_section(&mut open_sections, &mut root_symbols);
+            }
+
+            open_sections.push((level, heading_symbol(heading, line_index)));
+        }
+
+        let Some(end_line) = block_lines(block).map(|lines| *lines.end()) else {
+            continue;
+        };
+        // Unimarkup lines start at 1
+        let section_end = line_index.line_end(end_line.saturating_sub(1) as u32);
+
+        for (_, section_symbol) in open_sections.iter_mut() {
+            if section_symbol.range.end < section_end {
+                section_symbol.range.end = section_end;
+            }
+        }
+    }
+
+    while !open_sections.is_empty() {
+        close_section(&mut open_sections, &mut root_symbols);
+    }
+
+    root_symbols
+}
+
+/// Moves the innermost open section into its parent section,
+/// or into the root symbols if it has no parent.
+fn close_section(
+    open_sections: &mut Vec<(u8, DocumentSymbol)>,
+    root_symbols: &mut Vec<DocumentSymbol>,
+) {
+    let Some((_, symbol)) = open_sections.pop() else {
+        return;
+    };
+
+    match open_sections.last_mut() {
+        Some((_, parent)) => parent.children.get_or_insert_with(Vec::new).push(symbol),
+        None => root_symbols.push(symbol),
+    }
+}
+
+/// Creates the symbol of a heading, whose range only covers the heading itself.
+///
+/// The selection range covers the heading text after the level.
+#[allow(deprecated)]
+fn heading_symbol(heading: &Heading, line_index: &LineIndex) -> DocumentSymbol {
+    let line = heading.line_nr.saturating_sub(1) as u32;
+
+    let end = line_index.line_end(line);
+    let text_start = Position::new(
+        line,
+        line_index
+            .encoded_column(line, heading_content_column(heading, line_index) as u32)
+            .min(end.character),
+    );
+
+    let mut name = inlines_text(&heading.content);
+    if name.is_empty() {
+        name = heading.id.clone();
+    }
+
+    DocumentSymbol {
+        name,
+        detail: Some(heading.id.clone()),
+        kind: SymbolKind::STRING,
+        tags: None,
+        deprecated: None,
+        range: Range::new(Position::new(line, 0), end),
+        selection_range: Range::new(text_start, end),
+        children: None,
+    }
+}
+
+/// Converts nested symbols into a flat list in document order,
+/// with the name of the parent symbol as container name.
+pub fn flatten_symbols(uri: &Url, symbols: Vec<DocumentSymbol>) -> Vec<SymbolInformation> {
+    let mut flat_symbols = Vec::new();
+    push_flat_symbols(&mut flat_symbols, uri, symbols, None);
+    flat_symbols
+}
+
+#[allow(deprecated)]
+fn push_flat_symbols(
+    flat_symbols: &mut Vec<SymbolInformation>,
+    uri: &Url,
+    symbols: Vec<DocumentSymbol>,
+    container_name: Option<&str>,
+) {
+    for symbol in symbols {
+        flat_symbols.push(SymbolInformation {
+            name: symbol.name.clone(),
+            kind: symbol.kind,
+            tags: None,
+            deprecated: None,
+            location: Location::new(uri.clone(), symbol.range),
+            container_name: container_name.map(str::to_string),
+        });
+
+        if let Some(children) = symbol.children {
+            push_flat_symbols(flat_symbols, uri, children, Some(&symbol.name));
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use std::ops::RangeInclusive;
+
+use lsp_server::{RequestId, Response};
+use lsp_types::{
+    ClientCapabilities, DocumentSymbol, FoldingRange, FoldingRangeKind, FoldingRangeParams,
+};
+use unimarkup_core::{document::Document, elements::blocks::Block};
+use unimarkup_inline::Inline;
+
+use crate::blocks::block_lines;
+use crate::doc_sync::VersionedDocument;
+use crate::document_symbols::get_document_symbols;
+use crate::line_index::LineIndex;
+
+/// Prefix of Unimarkup line comments.
+pub const COMMENT_PREFIX: &str = ";;";
+
+/// Answers a `textDocument/foldingRange` request.
+pub(crate) fn get_folding_ranges_response(
+    id: RequestId,
+    _params: FoldingRangeParams,
+    document: Option<&VersionedDocument>,
+    support: FoldingSupport,
+) -> Response {
+    let ranges = document
+        .map(|um_doc| get_folding_ranges(&um_doc.doc, &um_doc.line_index, support))
+        .unwrap_or_default();
+
+    let result = serde_json::to_value(&ranges).unwrap();
+    Response {
+        id,
+        result: Some(result),
+        error: None,
+    }
+}
+
+/// Folding range features the client supports.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FoldingSupport {
+    /// `true` if the client ignores the start and end characters of folding ranges.
+    pub line_folding_only: bool,
+    /// Maximum number of folding ranges the client wants per document.
+    pub range_limit: Option<u32>,
+}
+
+impl FoldingSupport {
+    pub fn from_capabilities(capabilities: &ClientCapabilities) -> Self {
+        let folding_range = capabilities
+            .text_document
+            .as_ref()
+            .and_then(|text_document| text_document.folding_range.as_ref());
+
+        match folding_range {
+            Some(folding_range) => FoldingSupport {
+                line_folding_only: folding_range.line_folding_only == Some(true),
+                range_limit: folding_range.range_limit,
+            },
+            None => FoldingSupport::default(),
+        }
+    }
+}
+
+/// Creates folding ranges for heading sections, verbatim blocks,
+/// inlines spanning multiple lines and runs of comment lines.
+///
+/// Ranges are ordered by their start line. If the client set a range limit,
+/// ranges after the limit are dropped.
+pub fn get_folding_ranges(
+    document: &Document,
+    line_index: &LineIndex,
+    support: FoldingSupport,
+) -> Vec<FoldingRange> {
+    let mut ranges = Vec::new();
+
+    push_section_ranges(&mut ranges, &get_document_symbols(document, line_index));
+    push_block_ranges(&mut ranges, &document.blocks, line_index);
+
+    let verbatim_lines: Vec<RangeInclusive<usize>> = document
+        .blocks
+        .iter()
+        .filter(|block| matches!(block, Block::Verbatim(_)))
+        .filter_map(block_lines)
+        .collect();
+    push_comment_ranges(&mut ranges, line_index, &verbatim_lines);
+
+    if support.line_folding_only {
+        ranges = ranges.into_iter().filter_map(line_folding).collect();
+    }
+
+    // Outer ranges first on equal start lines
+    ranges.sort_by(|range, other| {
+        range
+            .start_line
+            .cmp(&other.start_line)
+            .then_with(|| other.end_line.cmp(&range.end_line))
+    });
+
+    if support.line_folding_only {
+        // Ranges of nested inlines may fold the same lines
+        ranges.dedup_by(|range, other| {
+            range.start_line == other.start_line && range.end_line == other.end_line
+        });
+    }
+
+    if let Some(range_limit) = support.range_limit {
+        ranges.truncate(range_limit as usize);
+    }
+
+    ranges
+}
+
+/// Folds the section of every heading, which ends before the next heading of the same or lower level.
+fn push_section_ranges(ranges: &mut Vec<FoldingRange>, symbols: &[DocumentSymbol]) {
+    for symbol in symbols {
+        if symbol.range.end.line > symbol.range.start.line {
+            ranges.push(FoldingRange {
+                start_line: symbol.range.start.line,
+                end_line: symbol.range.end.line,
+                ..Default::default()
+            });
+        }
+
+        if let Some(children) = &symbol.children {
+            push_section_ranges(ranges, children);
+        }
+    }
+}
+
+/// Folds verbatim blocks from fence to fence, and inlines of all blocks that span multiple lines.
+fn push_block_ranges(ranges: &mut Vec<FoldingRange>, blocks: &[Block], line_index: &LineIndex) {
+    for block in blocks {
+        match block {
+            Block::Verbatim(_) => {
+                if let Some(lines) = block_lines(block) {
+                    // Unimarkup lines start at 1
+                    ranges.push(FoldingRange {
+                        start_line: lines.start().saturating_sub(1) as u32,
+                        end_line: lines.end().saturating_sub(1) as u32,
+                        ..Default::default()
+                    });
+                }
+            }
+            Block::Paragraph(paragraph) => {
+                push_inline_ranges(ranges, paragraph.content.iter(), line_index)
+            }
+            Block::BulletList(bullet_list) => {
+                for entry in &bullet_list.entries {
+                    push_inline_ranges(ranges, entry.heading.iter(), line_index);
+                    push_block_ranges(ranges, &entry.body, line_index);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Folds inlines that span multiple lines from their opening to their closing delimiter.
+fn push_inline_ranges<'a>(
+    ranges: &mut Vec<FoldingRange>,
+    inlines: impl Iterator<Item = &'a Inline>,
+    line_index: &LineIndex,
+) {
+    for inline in inlines {
+        let nested = match inline {
+            Inline::Bold(nested)
+            | Inline::Italic(nested)
+            | Inline::Underline(nested)
+            | Inline::Subscript(nested)
+            | Inline::Superscript(nested)
+            | Inline::Overline(nested)
+            | Inline::Strikethrough(nested)
+            | Inline::Highlight(nested)
+            | Inline::Quote(nested)
+            | Inline::TextGroup(nested)
+            | Inline::Attributes(nested)
+            | Inline::Substitution(nested)
+            | Inline::Parentheses(nested) => Some(nested),
+            Inline::Multiple(nested) => {
+                // Only groups other inlines, so it has no delimiters to fold
+                push_inline_ranges(ranges, nested.iter(), line_index);
+                continue;
+            }
+            Inline::Verbatim(_) | Inline::Math(_) => None,
+            Inline::Plain(_)
+            | Inline::Whitespace(_)
+            | Inline::Newline(_)
+            | Inline::EndOfLine(_) => {
+                continue;
+            }
+        };
+
+        let span = inline.span();
+        if span.end().line > span.start().line {
+            // Unimarkup positions start at 1, and span ends include the last character
+            let start_line = span.start().line.saturating_sub(1) as u32;
+            let end_line = span.end().line.saturating_sub(1) as u32;
+
+            ranges.push(FoldingRange {
+                start_line,
+                start_character: Some(
+                    line_index
+                        .encoded_column(start_line, span.start().column.saturating_sub(1) as u32),
+                ),
+                end_line,
+                end_character: Some(line_index.encoded_column(end_line, span.end().column as u32)),
+                ..Default::default()
+            });
+        }
+
+        if let Some(nested) = nested {
+            push_inline_ranges(ranges, nested.iter(), line_index);
+        }
+    }
+}
+
+/// Folds runs of at least two consecutive comment lines.
+///
+/// Lines inside verbatim blocks are never comments.
+fn push_comment_ranges(
+    ranges: &mut Vec<FoldingRange>,
+    line_index: &LineIndex,
+    verbatim_lines: &[RangeInclusive<usize>],
+) {
+    let is_comment = |line: u32| {
+        // Unimarkup lines start at 1
+        let um_line = line as usize + 1;
+        line_index
+            .line(line)
+            .trim_start()
+            .starts_with(COMMENT_PREFIX)
+            && !verbatim_lines.iter().any(|lines| lines.contains(&um_line))
+    };
+
+    let mut run_start: Option<u32> = None;
+    for line in 0..=line_index.line_count() {
+        match (
+            run_start,
+            line < line_index.line_count() && is_comment(line),
+        ) {
+            (None, true) => run_start = Some(line),
+            (Some(start_line), false) => {
+                if line - 1 > start_line {
+                    ranges.push(FoldingRange {
+                        start_line,
+                        end_line: line - 1,
+                        kind: Some(FoldingRangeKind::Comment),
+                        ..Default::default()
+                    });
+                }
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Converts a folding range for clients that only fold complete lines.
+///
+/// Ranges ending inside a line end on the previous line,
+/// so folding does not hide the text after the range end.
+/// Returns `None` if no complete line is left to fold.
+fn line_folding(mut range: FoldingRange) -> Option<FoldingRange> {
+    if range.end_character.is_some() {
+        range.end_line = range.end_line.checked_sub(1)?;
+    }
+
+    range.start_character = None;
+    range.end_character = None;
+
+    (range.end_line > range.start_line).then_some(range)
+}
@@ -1,29 +1,72 @@
 use std::collections::HashMap;
 use std::error::Error;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use tokio::sync::{mpsc, RwLock};
-use unimarkup_core::document::Document;
 
-use lsp_server::{Connection, Message, RequestId};
-use lsp_types::notification::DidOpenTextDocument;
-use lsp_types::request::SemanticTokensFullRequest;
+use lsp_server::{Connection, Message, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeConfiguration, DidChangeWatchedFiles, DidCloseTextDocument, DidOpenTextDocument,
+    PublishDiagnostics,
+};
+use lsp_types::request::{
+    DocumentDiagnosticRequest, DocumentSymbolRequest, FoldingRangeRequest, GotoDefinition,
+    SelectionRangeRequest, SemanticTokensFullDeltaRequest, SemanticTokensFullRequest,
+    SemanticTokensRangeRequest, WorkspaceSymbolRequest, WorkspaceSymbolResolve,
+};
 use lsp_types::{
     notification::{DidChangeTextDocument, Notification},
     request::Request,
     InitializeParams,
 };
 use lsp_types::{
-    DidChangeTextDocumentParams, DidOpenTextDocumentParams, SemanticTokensParams, Url,
+    ConfigurationItem, ConfigurationParams, Diagnostic, DidChangeConfigurationParams,
+    DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
+    DidChangeWatchedFilesRegistrationOptions, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentDiagnosticParams, DocumentSymbolParams, FileChangeType,
+    FileSystemWatcher, FoldingRangeParams, GlobPattern, GotoDefinitionParams,
+    PublishDiagnosticsParams, Registration, RegistrationParams, SelectionRangeParams,
+    SemanticTokensDeltaParams, SemanticTokensParams, SemanticTokensRangeParams, Url,
+    WorkspaceSymbol, WorkspaceSymbolParams,
 };
 use serde::Serialize;
 
-use self::doc_sync::DocChangeWorker;
-use self::semantic_tokens::get_semantic_tokens_response;
+use self::definitions::{definition_link_supported, get_definition_response};
+use self::diagnostics::{get_diagnostics_response, DiagnosticsStore};
+use self::doc_sync::{
+    remove_indexed_document, store_document, DocChangeWorker, DocEvent, DocUpdate,
+    VersionedDocument,
+};
+use self::document_symbols::{get_document_symbols_response, hierarchical_symbols_supported};
+use self::folding_ranges::{get_folding_ranges_response, FoldingSupport};
+use self::line_index::PositionEncoding;
+use self::requests::{PendingRequests, RefreshThrottle, ServerRequest};
+use self::selection_ranges::get_selection_ranges_response;
+use self::semantic_tokens::{
+    get_semantic_tokens_delta_response, get_semantic_tokens_range_response,
+    get_semantic_tokens_response, SemanticTokensCache, SemanticTokensSupport,
+};
+use self::settings::{Settings, SETTINGS_SECTION};
+use self::workspace::{find_um_files, is_um_file, UM_FILE_EXTENSION};
+use self::workspace_symbols::{
+    get_workspace_symbol_resolve_response, get_workspace_symbols_response,
+    workspace_symbol_resolve_supported,
+};
 
+mod blocks;
 mod capabilities;
-mod doc_sync;
+pub mod definitions;
+pub mod diagnostics;
+pub mod doc_sync;
+pub mod document_symbols;
+pub mod folding_ranges;
+pub mod line_index;
+pub mod requests;
+pub mod selection_ranges;
 pub mod semantic_tokens;
+pub mod settings;
+pub mod workspace;
+pub mod workspace_symbols;
 
 #[derive(Debug, Clone, Serialize)]
 struct RenderedContent {
@@ -31,18 +74,28 @@ struct RenderedContent {
     content: String,
 }
 
+#[derive(Debug, Clone, Serialize)]
+struct ClosedContent {
+    id: Url,
+}
+
 pub fn run() -> Result<(), Box<dyn Error + Sync + Send>> {
     let (connection, io_threads) = Connection::stdio();
 
-    let serialized_server_capabilities =
-        serde_json::to_value(capabilities::get_capabilities()).unwrap();
+    let (initialize_id, initialization_params) = connection.initialize_start()?;
+    let params: InitializeParams = serde_json::from_value(initialization_params)?;
+
+    let position_encoding = PositionEncoding::negotiate(&params.capabilities);
+    let initialize_data = serde_json::json!({
+        "capabilities": capabilities::get_capabilities(position_encoding, &params.capabilities),
+    });
 
-    let initialization_params = connection.initialize(serialized_server_capabilities)?;
+    connection.initialize_finish(initialize_id, initialize_data)?;
 
     let runtime = tokio::runtime::Runtime::new()?;
 
     runtime.block_on(async {
-        let _ = main_loop(connection, initialization_params).await;
+        let _ = main_loop(connection, params, position_encoding).await;
         io_threads.join()
     })?;
 
@@ -51,43 +104,225 @@ pub fn run() -> Result<(), Box<dyn Error + Sync + Send>> {
 
 async fn main_loop(
     connection: Connection,
-    params: serde_json::Value,
+    params: InitializeParams,
+    position_encoding: PositionEncoding,
 ) -> Result<(), Box<dyn Error + Sync + Send>> {
-    let params: InitializeParams = serde_json::from_value(params).unwrap();
+    let mut semantic_tokens_refresh_supported = false;
+    let mut diagnostic_refresh_supported = false;
+    let mut configuration_supported = false;
+    let mut configuration_registration_supported = false;
+    let mut file_watcher_registration_supported = false;
 
-    let mut semantic_tokens_supported = false;
+    let semantic_tokens_support = SemanticTokensSupport::from_capabilities(&params.capabilities);
+    let hierarchical_symbols = hierarchical_symbols_supported(&params.capabilities);
+    let workspace_symbol_resolve = workspace_symbol_resolve_supported(&params.capabilities);
+    let folding_support = FoldingSupport::from_capabilities(&params.capabilities);
+    let definition_links = definition_link_supported(&params.capabilities);
+
+    // Clients that support pull diagnostics request them on their own
+    let push_diagnostics = params
+        .capabilities
+        .text_document
+        .as_ref()
+        .and_then(|text_document_capabilities| text_document_capabilities.diagnostic.as_ref())
+        .is_none();
 
     if let Some(workspace_capabilities) = params.capabilities.workspace {
-        semantic_tokens_supported = workspace_capabilities.semantic_tokens.is_some();
+        semantic_tokens_refresh_supported = workspace_capabilities
+            .semantic_tokens
+            .and_then(|semantic_tokens| semantic_tokens.refresh_support)
+            == Some(true);
+        diagnostic_refresh_supported = workspace_capabilities
+            .diagnostic
+            .and_then(|diagnostic| diagnostic.refresh_support)
+            == Some(true);
+        configuration_supported = workspace_capabilities.configuration == Some(true);
+        configuration_registration_supported = workspace_capabilities
+            .did_change_configuration
+            .and_then(|did_change_configuration| did_change_configuration.dynamic_registration)
+            == Some(true);
+        file_watcher_registration_supported = workspace_capabilities
+            .did_change_watched_files
+            .and_then(|did_change_watched_files| did_change_watched_files.dynamic_registration)
+            == Some(true);
     }
 
-    let (tx_um, mut rx_um) = mpsc::channel::<Document>(10);
-    let (tx_doc_open, rx_doc_open) = mpsc::channel::<DidOpenTextDocumentParams>(10);
-    let (tx_doc_change, rx_doc_change) = mpsc::channel::<DidChangeTextDocumentParams>(10);
+    let (tx_um, mut rx_um) = mpsc::channel::<DocUpdate>(10);
+    // Unbounded, so the main loop never waits on the worker
+    let (tx_doc_event, rx_doc_event) = mpsc::unbounded_channel::<DocEvent>();
     let (tx_shutdown, _rx_shutdown) = mpsc::channel::<bool>(10);
 
-    let parsed_documents: Arc<RwLock<HashMap<Url, Document>>> =
+    let parsed_documents: Arc<RwLock<HashMap<Url, VersionedDocument>>> =
         Arc::new(RwLock::new(HashMap::new()));
-    let mut update_cnt = 0;
+    let doc_diagnostics = Arc::new(RwLock::new(DiagnosticsStore::default()));
+    let mut semantic_tokens_cache = SemanticTokensCache::default();
 
     let conn = Arc::new(connection);
 
-    DocChangeWorker::init(tx_um, rx_doc_open, rx_doc_change);
+    let workspace_roots: Vec<PathBuf> = match &params.workspace_folders {
+        Some(workspace_folders) => workspace_folders
+            .iter()
+            .filter_map(|workspace_folder| workspace_folder.uri.to_file_path().ok())
+            .collect(),
+        None => params
+            .root_uri
+            .iter()
+            .filter_map(|root_uri| root_uri.to_file_path().ok())
+            .collect(),
+    };
+
+    let settings = params
+        .initialization_options
+        .as_ref()
+        .and_then(Settings::from_value)
+        .unwrap_or_default();
+
+    DocChangeWorker::init(tx_um, rx_doc_event, &settings, position_encoding);
+
+    // Index all Unimarkup files of the workspace in the background
+    let tx_index = tx_doc_event.clone();
+    let index_roots = workspace_roots.clone();
+    tokio::task::spawn_blocking(move || {
+        for um_file in index_roots.iter().flat_map(|root| find_um_files(root)) {
+            if let Ok(uri) = Url::from_file_path(um_file) {
+                let _ = tx_index.send(DocEvent::Index(uri));
+            }
+        }
+    });
+
+    // Shared with the refresh task, which sends requests to the client as well
+    let pending_requests = Arc::new(Mutex::new(PendingRequests::default()));
+
+    let mut registrations = Vec::new();
+
+    if configuration_registration_supported {
+        registrations.push(Registration {
+            id: DidChangeConfiguration::METHOD.to_string(),
+            method: DidChangeConfiguration::METHOD.to_string(),
+            register_options: None,
+        });
+    }
+
+    if file_watcher_registration_supported {
+        let watchers = vec![FileSystemWatcher {
+            glob_pattern: GlobPattern::String(format!("**/*.{}", UM_FILE_EXTENSION)),
+            kind: None,
+        }];
+
+        registrations.push(Registration {
+            id: DidChangeWatchedFiles::METHOD.to_string(),
+            method: DidChangeWatchedFiles::METHOD.to_string(),
+            register_options: Some(
+                serde_json::to_value(DidChangeWatchedFilesRegistrationOptions { watchers })
+                    .unwrap(),
+            ),
+        });
+    }
+
+    if !registrations.is_empty() {
+        let request = pending_requests.lock().unwrap().create(
+            ServerRequest::RegisterCapability,
+            serde_json::to_value(RegistrationParams { registrations }).unwrap(),
+        );
+        conn.sender.send(Message::Request(request))?;
+    }
+
+    if configuration_supported {
+        request_settings(&conn, &pending_requests)?;
+    }
+
+    let (tx_refresh, rx_refresh) = mpsc::unbounded_channel::<RefreshEvent>();
+    if semantic_tokens_refresh_supported {
+        tokio::spawn(refresh_loop(
+            Arc::clone(&conn),
+            Arc::clone(&pending_requests),
+            ServerRequest::SemanticTokensRefresh,
+            rx_refresh,
+        ));
+    }
+
+    // Pull diagnostics are only requested again once the client is asked to refresh them
+    let refresh_diagnostics = !push_diagnostics && diagnostic_refresh_supported;
+    let (tx_diagnostic_refresh, rx_diagnostic_refresh) = mpsc::unbounded_channel::<RefreshEvent>();
+    if refresh_diagnostics {
+        tokio::spawn(refresh_loop(
+            Arc::clone(&conn),
+            Arc::clone(&pending_requests),
+            ServerRequest::DiagnosticRefresh,
+            rx_diagnostic_refresh,
+        ));
+    }
 
     let conn2 = Arc::clone(&conn);
+    let tx_refresh_answered = tx_refresh.clone();
+    let tx_diagnostic_refresh_answered = tx_diagnostic_refresh.clone();
     let mut ren_docs = Arc::clone(&parsed_documents);
+    let diagnostics_store = Arc::clone(&doc_diagnostics);
     tokio::spawn(async move {
         loop {
-            if let Some(um) = rx_um.recv().await {
-                update_cnt += 1;
-                let _ = update_um_file(
-                    um,
-                    &conn2,
-                    &mut ren_docs,
-                    semantic_tokens_supported,
-                    update_cnt,
-                )
-                .await;
+            match rx_um.recv().await {
+                Some(DocUpdate::Compiled(um)) => {
+                    let file_id = um.uri.clone();
+                    let version = um.version;
+
+                    let opened_doc_updated = update_um_file(*um, &conn2, &mut ren_docs)
+                        .await
+                        .unwrap_or(false);
+
+                    if opened_doc_updated && semantic_tokens_refresh_supported {
+                        let _ = tx_refresh.send(RefreshEvent::Requested);
+                    }
+
+                    // Diagnostics are only reported for opened documents
+                    if version.is_none() {
+                        continue;
+                    }
+
+                    let changed = diagnostics_store
+                        .write()
+                        .await
+                        .update(file_id.clone(), vec![]);
+
+                    if changed && push_diagnostics {
+                        let _ = publish_diagnostics(&conn2, file_id, vec![], version);
+                    } else if changed && refresh_diagnostics {
+                        let _ = tx_diagnostic_refresh.send(RefreshEvent::Requested);
+                    }
+                }
+                Some(DocUpdate::Failed {
+                    uri,
+                    version,
+                    diagnostics,
+                }) => {
+                    // The outdated result of an indexed file must not be used anymore
+                    if version.is_none() {
+                        remove_indexed_document(&mut *ren_docs.write().await, &uri);
+                        continue;
+                    }
+
+                    let changed = diagnostics_store
+                        .write()
+                        .await
+                        .update(uri.clone(), diagnostics.clone());
+
+                    if changed && push_diagnostics {
+                        let _ = publish_diagnostics(&conn2, uri, diagnostics, version);
+                    } else if changed && refresh_diagnostics {
+                        let _ = tx_diagnostic_refresh.send(RefreshEvent::Requested);
+                    }
+                }
+                Some(DocUpdate::Closed(uri)) => {
+                    let had_diagnostics = diagnostics_store.write().await.remove(&uri);
+                    let _ = close_um_file(uri, &conn2, &mut ren_docs, push_diagnostics).await;
+
+                    if had_diagnostics && refresh_diagnostics {
+                        let _ = tx_diagnostic_refresh.send(RefreshEvent::Requested);
+                    }
+                }
+                Some(DocUpdate::Removed(uri)) => {
+                    remove_indexed_document(&mut *ren_docs.write().await, &uri);
+                }
+                None => break,
             }
         }
     });
@@ -102,23 +337,167 @@ async fn main_loop(
             let connection = Arc::clone(&conn);
 
             match handle_msg(msg, &connection)? {
-                LspAction::SendSemanticTokens {
-                    id,
-                    params,
-                    file_path,
-                } => {
+                LspAction::SendSemanticTokens { id, params } => {
+                    let documents = parsed_documents.read().await;
+                    let document = documents.get(&params.text_document.uri);
+
+                    let resp = get_semantic_tokens_response(
+                        id,
+                        params,
+                        document,
+                        semantic_tokens_support,
+                        &mut semantic_tokens_cache,
+                    );
+                    connection.sender.send(Message::Response(resp))?;
+                }
+                LspAction::SendSemanticTokensRange { id, params } => {
+                    let documents = parsed_documents.read().await;
+                    let document = documents.get(&params.text_document.uri);
+
+                    let resp = get_semantic_tokens_range_response(
+                        id,
+                        params,
+                        document,
+                        semantic_tokens_support,
+                    );
+                    connection.sender.send(Message::Response(resp))?;
+                }
+                LspAction::SendSemanticTokensDelta { id, params } => {
+                    let documents = parsed_documents.read().await;
+                    let document = documents.get(&params.text_document.uri);
+
+                    let resp = get_semantic_tokens_delta_response(
+                        id,
+                        params,
+                        document,
+                        semantic_tokens_support,
+                        &mut semantic_tokens_cache,
+                    );
+                    connection.sender.send(Message::Response(resp))?;
+                }
+                LspAction::SendDocumentSymbols { id, params } => {
+                    let documents = parsed_documents.read().await;
+                    let document = documents.get(&params.text_document.uri);
+
+                    let resp =
+                        get_document_symbols_response(id, params, document, hierarchical_symbols);
+                    connection.sender.send(Message::Response(resp))?;
+                }
+                LspAction::SendDefinition { id, params } => {
+                    let documents = parsed_documents.read().await;
+
+                    let resp = get_definition_response(id, params, &documents, definition_links);
+                    connection.sender.send(Message::Response(resp))?;
+                }
+                LspAction::SendFoldingRanges { id, params } => {
+                    let documents = parsed_documents.read().await;
+                    let document = documents.get(&params.text_document.uri);
+
+                    let resp = get_folding_ranges_response(id, params, document, folding_support);
+                    connection.sender.send(Message::Response(resp))?;
+                }
+                LspAction::SendSelectionRanges { id, params } => {
+                    let documents = parsed_documents.read().await;
+                    let document = documents.get(&params.text_document.uri);
+
+                    let resp = get_selection_ranges_response(id, params, document);
+                    connection.sender.send(Message::Response(resp))?;
+                }
+                LspAction::SendWorkspaceSymbols { id, params } => {
+                    let documents = parsed_documents.read().await;
+
+                    let resp = get_workspace_symbols_response(
+                        id,
+                        params,
+                        &documents,
+                        workspace_symbol_resolve,
+                    );
+                    connection.sender.send(Message::Response(resp))?;
+                }
+                LspAction::ResolveWorkspaceSymbol { id, symbol } => {
                     let documents = parsed_documents.read().await;
-                    let document = documents.get(&Url::from_file_path(file_path).unwrap());
 
-                    let resp = get_semantic_tokens_response(id, params, document);
+                    let resp = get_workspace_symbol_resolve_response(id, symbol, &documents);
                     connection.sender.send(Message::Response(resp))?;
                 }
+                LspAction::SendDiagnostics { id, params } => {
+                    let diagnostics = doc_diagnostics.read().await;
+                    let resp = get_diagnostics_response(
+                        id,
+                        params.clone(),
+                        diagnostics.get(&params.text_document.uri),
+                    );
+                    connection.sender.send(Message::Response(resp))?;
+                }
+                LspAction::HandleResponse(resp) => {
+                    let request = pending_requests.lock().unwrap().complete(&resp);
+
+                    match request {
+                        Some(ServerRequest::Configuration) => {
+                            // The client answers with one value per requested configuration item
+                            let settings = resp
+                                .result
+                                .as_ref()
+                                .and_then(|result| result.get(0))
+                                .and_then(Settings::from_value);
+
+                            if let Some(settings) = settings {
+                                tx_doc_event.send(DocEvent::Reconfigure(settings))?;
+                            }
+                        }
+                        Some(ServerRequest::SemanticTokensRefresh) => {
+                            let _ = tx_refresh_answered.send(RefreshEvent::Answered);
+                        }
+                        Some(ServerRequest::DiagnosticRefresh) => {
+                            let _ = tx_diagnostic_refresh_answered.send(RefreshEvent::Answered);
+                        }
+                        Some(ServerRequest::RegisterCapability) => {}
+                        None => eprintln!("Response to unknown request: {:?}", resp),
+                    }
+                }
+                LspAction::ChangeConfig(params) => {
+                    // Clients supporting `workspace/configuration` may not send the settings
+                    // with the notification, so they are always requested
+                    if configuration_supported {
+                        request_settings(&connection, &pending_requests)?;
+                    } else if let Some(settings) = Settings::from_value(&params.settings) {
+                        tx_doc_event.send(DocEvent::Reconfigure(settings))?;
+                    }
+                }
+                LspAction::WatchedFilesChanged(params) => {
+                    for change in params.changes {
+                        let Ok(file_path) = change.uri.to_file_path() else {
+                            continue;
+                        };
+
+                        if in_workspace(&file_path, &workspace_roots) {
+                            if change.typ == FileChangeType::DELETED {
+                                tx_doc_event.send(DocEvent::Remove(change.uri))?;
+                            } else {
+                                tx_doc_event.send(DocEvent::Index(change.uri))?;
+                            }
+                        }
+                    }
+                }
                 LspAction::UpdateDoc(params) => {
-                    tx_doc_change.send(params).await?;
+                    tx_doc_event.send(DocEvent::Change(params))?;
                     continue;
                 }
                 LspAction::OpenDoc(params) => {
-                    tx_doc_open.send(params).await?;
+                    tx_doc_event.send(DocEvent::Open(params))?;
+                    continue;
+                }
+                LspAction::CloseDoc(params) => {
+                    let uri = params.text_document.uri.clone();
+                    semantic_tokens_cache.remove(&uri);
+                    tx_doc_event.send(DocEvent::Close(params))?;
+
+                    // Closed workspace files are kept in the index with their content on disk
+                    if let Ok(file_path) = uri.to_file_path() {
+                        if file_path.is_file() && in_workspace(&file_path, &workspace_roots) {
+                            tx_doc_event.send(DocEvent::Index(uri))?;
+                        }
+                    }
                     continue;
                 }
                 LspAction::Shutdown => {
@@ -132,35 +511,155 @@ async fn main_loop(
     }
 }
 
+/// Stores a compiled document and sends the rendered content of opened documents to the preview.
+///
+/// Returns `true` if the stored content of an opened document changed.
 async fn update_um_file(
-    um: Document,
+    um: VersionedDocument,
+    conn: &Connection,
+    rendered_documents: &mut Arc<RwLock<HashMap<Url, VersionedDocument>>>,
+) -> Result<bool, Box<dyn Error + Sync + Send>> {
+    let file_id = um.uri.clone();
+
+    {
+        let mut documents = rendered_documents.write().await;
+
+        if !store_document(&mut documents, um) {
+            return Ok(false);
+        }
+
+        // Preview and semantic tokens are only needed for opened documents
+        let Some(opened_doc) = documents.get(&file_id).filter(|um| um.version.is_some()) else {
+            return Ok(false);
+        };
+
+        let rendered_content = RenderedContent {
+            id: file_id.clone(),
+            content: opened_doc.doc.html().body,
+        };
+
+        let resp = lsp_server::Notification {
+            method: "extension/renderedContent".to_string(),
+            params: serde_json::to_value(rendered_content).unwrap(),
+        };
+
+        conn.sender.send(Message::Notification(resp))?;
+    }
+
+    Ok(true)
+}
+
+/// Events handled by the [`refresh_loop`].
+enum RefreshEvent {
+    /// The semantic tokens or diagnostics of an opened document changed.
+    Requested,
+    /// The client answered the last refresh request.
+    Answered,
+}
+
+/// Sends the given refresh request to the client after documents changed.
+///
+/// Refreshes are throttled by the [`RefreshThrottle`],
+/// so a burst of document updates only results in one refresh request.
+async fn refresh_loop(
+    conn: Arc<Connection>,
+    pending_requests: Arc<Mutex<PendingRequests>>,
+    refresh_request: ServerRequest,
+    mut rx_refresh: mpsc::UnboundedReceiver<RefreshEvent>,
+) {
+    let mut throttle = RefreshThrottle::default();
+
+    loop {
+        let next_refresh = throttle.next_refresh(tokio::time::Instant::now());
+
+        tokio::select! {
+            event = rx_refresh.recv() => match event {
+                Some(RefreshEvent::Requested) => throttle.request(),
+                Some(RefreshEvent::Answered) => throttle.answered(),
+                None => break,
+            },
+            _ = tokio::time::sleep_until(next_refresh.unwrap_or_else(tokio::time::Instant::now)),
+                if next_refresh.is_some() => {
+                let request = pending_requests
+                    .lock()
+                    .unwrap()
+                    .create(refresh_request, serde_json::Value::Null);
+
+                if conn.sender.send(Message::Request(request)).is_err() {
+                    break;
+                }
+
+                throttle.sent(tokio::time::Instant::now());
+            }
+        }
+    }
+}
+
+/// Removes a closed document from the document store,
+/// clears its pushed diagnostics and informs the preview that the document is gone.
+async fn close_um_file(
+    file_id: Url,
     conn: &Connection,
-    rendered_documents: &mut Arc<RwLock<HashMap<Url, Document>>>,
-    semantic_tokens_supported: bool,
-    update_cnt: usize,
+    rendered_documents: &mut Arc<RwLock<HashMap<Url, VersionedDocument>>>,
+    push_diagnostics: bool,
 ) -> Result<(), Box<dyn Error + Sync + Send>> {
-    let file_id = Url::from_file_path(um.config.um_file.clone()).unwrap();
-    let rendered_content = RenderedContent {
-        id: file_id.clone(),
-        content: um.html().body,
-    };
+    rendered_documents.write().await.remove(&file_id);
+
+    if push_diagnostics {
+        publish_diagnostics(conn, file_id.clone(), vec![], None)?;
+    }
 
-    rendered_documents.write().await.insert(file_id, um);
+    let closed_content = ClosedContent { id: file_id };
+    conn.sender
+        .send(Message::Notification(lsp_server::Notification {
+            method: "extension/closedContent".to_string(),
+            params: serde_json::to_value(closed_content).unwrap(),
+        }))?;
+
+    Ok(())
+}
 
-    let resp = lsp_server::Notification {
-        method: "extension/renderedContent".to_string(),
-        params: serde_json::to_value(rendered_content).unwrap(),
+/// Returns `true` if the given path is a Unimarkup file inside one of the workspace roots.
+fn in_workspace(file_path: &Path, workspace_roots: &[PathBuf]) -> bool {
+    is_um_file(file_path)
+        && workspace_roots
+            .iter()
+            .any(|workspace_root| file_path.starts_with(workspace_root))
+}
+
+/// Requests the server settings from the client with `workspace/configuration`.
+fn request_settings(
+    conn: &Connection,
+    pending_requests: &Mutex<PendingRequests>,
+) -> Result<(), Box<dyn Error + Sync + Send>> {
+    let params = ConfigurationParams {
+        items: vec![ConfigurationItem {
+            scope_uri: None,
+            section: Some(SETTINGS_SECTION.to_string()),
+        }],
     };
 
-    conn.sender.send(Message::Notification(resp))?;
+    let request = pending_requests.lock().unwrap().create(
+        ServerRequest::Configuration,
+        serde_json::to_value(params).unwrap(),
+    );
+    conn.sender.send(Message::Request(request))?;
+
+    Ok(())
+}
 
-    if semantic_tokens_supported {
-        conn.sender.send(Message::Request(lsp_server::Request {
-            id: format!("doc-update-{}", update_cnt).into(),
-            method: "workspace/semanticTokens/refresh".to_string(),
-            params: serde_json::Value::Null,
+fn publish_diagnostics(
+    conn: &Connection,
+    file_id: Url,
+    diagnostics: Vec<Diagnostic>,
+    version: Option<i32>,
+) -> Result<(), Box<dyn Error + Sync + Send>> {
+    let diagnostics = PublishDiagnosticsParams::new(file_id, diagnostics, version);
+    conn.sender
+        .send(Message::Notification(lsp_server::Notification {
+            method: PublishDiagnostics::METHOD.to_string(),
+            params: serde_json::to_value(diagnostics).unwrap(),
         }))?;
-    }
 
     Ok(())
 }
@@ -169,10 +668,49 @@ enum LspAction {
     SendSemanticTokens {
         id: RequestId,
         params: SemanticTokensParams,
-        file_path: PathBuf,
     },
+    SendSemanticTokensRange {
+        id: RequestId,
+        params: SemanticTokensRangeParams,
+    },
+    SendSemanticTokensDelta {
+        id: RequestId,
+        params: SemanticTokensDeltaParams,
+    },
+    SendDocumentSymbols {
+        id: RequestId,
+        params: DocumentSymbolParams,
+    },
+    SendDefinition {
+        id: RequestId,
+        params: GotoDefinitionParams,
+    },
+    SendFoldingRanges {
+        id: RequestId,
+        params: FoldingRangeParams,
+    },
+    SendSelectionRanges {
+        id: RequestId,
+        params: SelectionRangeParams,
+    },
+    SendWorkspaceSymbols {
+        id: RequestId,
+        params: WorkspaceSymbolParams,
+    },
+    ResolveWorkspaceSymbol {
+        id: RequestId,
+        symbol: WorkspaceSymbol,
+    },
+    SendDiagnostics {
+        id: RequestId,
+        params: DocumentDiagnosticParams,
+    },
+    HandleResponse(Response),
+    ChangeConfig(DidChangeConfigurationParams),
+    WatchedFilesChanged(DidChangeWatchedFilesParams),
     UpdateDoc(DidChangeTextDocumentParams),
     OpenDoc(DidOpenTextDocumentParams),
+    CloseDoc(DidCloseTextDocumentParams),
     Shutdown,
     Continue,
 }
@@ -189,13 +727,79 @@ fn handle_msg(
                 if let Ok((id, params)) =
                     req.extract::<SemanticTokensParams>(SemanticTokensFullRequest::METHOD)
                 {
-                    let file_path = params.text_document.uri.to_file_path().unwrap();
-
-                    Ok(LspAction::SendSemanticTokens {
-                        id,
-                        params,
-                        file_path,
-                    })
+                    Ok(LspAction::SendSemanticTokens { id, params })
+                } else {
+                    Ok(LspAction::Continue)
+                }
+            } else if let SemanticTokensRangeRequest::METHOD = req.method.as_str() {
+                if let Ok((id, params)) =
+                    req.extract::<SemanticTokensRangeParams>(SemanticTokensRangeRequest::METHOD)
+                {
+                    Ok(LspAction::SendSemanticTokensRange { id, params })
+                } else {
+                    Ok(LspAction::Continue)
+                }
+            } else if let SemanticTokensFullDeltaRequest::METHOD = req.method.as_str() {
+                if let Ok((id, params)) =
+                    req.extract::<SemanticTokensDeltaParams>(SemanticTokensFullDeltaRequest::METHOD)
+                {
+                    Ok(LspAction::SendSemanticTokensDelta { id, params })
+                } else {
+                    Ok(LspAction::Continue)
+                }
+            } else if let DocumentSymbolRequest::METHOD = req.method.as_str() {
+                if let Ok((id, params)) =
+                    req.extract::<DocumentSymbolParams>(DocumentSymbolRequest::METHOD)
+                {
+                    Ok(LspAction::SendDocumentSymbols { id, params })
+                } else {
+                    Ok(LspAction::Continue)
+                }
+            } else if let GotoDefinition::METHOD = req.method.as_str() {
+                if let Ok((id, params)) =
+                    req.extract::<GotoDefinitionParams>(GotoDefinition::METHOD)
+                {
+                    Ok(LspAction::SendDefinition { id, params })
+                } else {
+                    Ok(LspAction::Continue)
+                }
+            } else if let FoldingRangeRequest::METHOD = req.method.as_str() {
+                if let Ok((id, params)) =
+                    req.extract::<FoldingRangeParams>(FoldingRangeRequest::METHOD)
+                {
+                    Ok(LspAction::SendFoldingRanges { id, params })
+                } else {
+                    Ok(LspAction::Continue)
+                }
+            } else if let SelectionRangeRequest::METHOD = req.method.as_str() {
+                if let Ok((id, params)) =
+                    req.extract::<SelectionRangeParams>(SelectionRangeRequest::METHOD)
+                {
+                    Ok(LspAction::SendSelectionRanges { id, params })
+                } else {
+                    Ok(LspAction::Continue)
+                }
+            } else if let WorkspaceSymbolRequest::METHOD = req.method.as_str() {
+                if let Ok((id, params)) =
+                    req.extract::<WorkspaceSymbolParams>(WorkspaceSymbolRequest::METHOD)
+                {
+                    Ok(LspAction::SendWorkspaceSymbols { id, params })
+                } else {
+                    Ok(LspAction::Continue)
+                }
+            } else if let WorkspaceSymbolResolve::METHOD = req.method.as_str() {
+                if let Ok((id, symbol)) =
+                    req.extract::<WorkspaceSymbol>(WorkspaceSymbolResolve::METHOD)
+                {
+                    Ok(LspAction::ResolveWorkspaceSymbol { id, symbol })
+                } else {
+                    Ok(LspAction::Continue)
+                }
+            } else if let DocumentDiagnosticRequest::METHOD = req.method.as_str() {
+                if let Ok((id, params)) =
+                    req.extract::<DocumentDiagnosticParams>(DocumentDiagnosticRequest::METHOD)
+                {
+                    Ok(LspAction::SendDiagnostics { id, params })
                 } else {
                     Ok(LspAction::Continue)
                 }
@@ -204,10 +808,7 @@ fn handle_msg(
                 Ok(LspAction::Continue)
             }
         }
-        Message::Response(resp) => {
-            eprintln!("got response: {:?}", resp);
-            Ok(LspAction::Continue)
-        }
+        Message::Response(resp) => Ok(LspAction::HandleResponse(resp)),
         Message::Notification(notification) => match notification.method.as_str() {
             DidChangeTextDocument::METHOD => {
                 if let Ok(params) = notification
@@ -227,6 +828,33 @@ fn handle_msg(
                     Ok(LspAction::Continue)
                 }
             }
+            DidChangeConfiguration::METHOD => {
+                if let Ok(params) = notification
+                    .extract::<DidChangeConfigurationParams>(DidChangeConfiguration::METHOD)
+                {
+                    Ok(LspAction::ChangeConfig(params))
+                } else {
+                    Ok(LspAction::Continue)
+                }
+            }
+            DidChangeWatchedFiles::METHOD => {
+                if let Ok(params) = notification
+                    .extract::<DidChangeWatchedFilesParams>(DidChangeWatchedFiles::METHOD)
+                {
+                    Ok(LspAction::WatchedFilesChanged(params))
+                } else {
+                    Ok(LspAction::Continue)
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                if let Ok(params) =
+                    notification.extract::<DidCloseTextDocumentParams>(DidCloseTextDocument::METHOD)
+                {
+                    Ok(LspAction::CloseDoc(params))
+                } else {
+                    Ok(LspAction::Continue)
+                }
+            }
             _ => {
                 eprintln!("Unsupported notification: {:?}", notification);
                 Ok(LspAction::Continue)
@@ -0,0 +1,229 @@
+use lsp_types::{ClientCapabilities, Position, PositionEncodingKind};
+
+/// Encoding the character offsets of LSP positions are counted in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PositionEncoding {
+    /// Offsets are counted in bytes.
+    Utf8,
+    /// Offsets are counted in UTF-16 code units.
+    ///
+    /// Default encoding of the LSP specification, and used if the client offers no other encoding.
+    #[default]
+    Utf16,
+    /// Offsets are counted in Unicode scalar values.
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// Picks the first encoding of the client's `general.positionEncodings` the server supports.
+    ///
+    /// Falls back to UTF-16, because every client must support it.
+    pub fn negotiate(capabilities: &ClientCapabilities) -> Self {
+        capabilities
+            .general
+            .as_ref()
+            .and_then(|general| general.position_encodings.as_ref())
+            .and_then(|encodings| encodings.iter().find_map(PositionEncoding::from_kind))
+            .unwrap_or_default()
+    }
+
+    pub fn from_kind(kind: &PositionEncodingKind) -> Option<Self> {
+        if *kind == PositionEncodingKind::UTF8 {
+            Some(PositionEncoding::Utf8)
+        } else if *kind == PositionEncodingKind::UTF16 {
+            Some(PositionEncoding::Utf16)
+        } else if *kind == PositionEncodingKind::UTF32 {
+            Some(PositionEncoding::Utf32)
+        } else {
+            None
+        }
+    }
+
+    pub fn kind(&self) -> PositionEncodingKind {
+        match self {
+            PositionEncoding::Utf8 => PositionEncodingKind::UTF8,
+            PositionEncoding::Utf16 => PositionEncodingKind::UTF16,
+            PositionEncoding::Utf32 => PositionEncodingKind::UTF32,
+        }
+    }
+
+    /// Number of code units the given character is encoded with.
+    pub fn char_len(&self, c: char) -> usize {
+        match self {
+            PositionEncoding::Utf8 => c.len_utf8(),
+            PositionEncoding::Utf16 => c.len_utf16(),
+            PositionEncoding::Utf32 => 1,
+        }
+    }
+}
+
+/// Converts character columns of Unimarkup elements into columns of the negotiated encoding.
+///
+/// Unimarkup counts columns in characters, but LSP clients count them in code units
+/// of the [`PositionEncoding`]. Both count lines the same.
+#[derive(Debug, Default, Clone)]
+pub struct LineIndex {
+    text: String,
+    /// Byte offsets of all line starts.
+    line_starts: Vec<usize>,
+    encoding: PositionEncoding,
+}
+
+impl LineIndex {
+    pub fn new(text: &str, encoding: PositionEncoding) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(offset, _)| offset + 1))
+            .collect();
+
+        LineIndex {
+            text: text.to_string(),
+            line_starts,
+            encoding,
+        }
+    }
+
+    pub fn encoding(&self) -> PositionEncoding {
+        self.encoding
+    }
+
+    /// Number of lines in the text.
+    pub fn line_count(&self) -> u32 {
+        self.line_starts.len() as u32
+    }
+
+    /// Returns the content of the given line without the line break.
+    ///
+    /// Lines start at 0. Lines after the end of the text are empty.
+    pub fn line(&self, line: u32) -> &str {
+        let Some(&start) = self.line_starts.get(line as usize) else {
+            return "";
+        };
+        let end = self
+            .line_starts
+            .get(line as usize + 1)
+            .map_or(self.text.len(), |next_start| next_start - 1);
+
+        let line = &self.text[start..end];
+        line.strip_suffix('\r').unwrap_or(line)
+    }
+
+    /// Returns the number of characters of the line break at the end of the given line.
+    ///
+    /// Lines end with `\n` or `\r\n`. The last line has no line break.
+    pub fn line_break_len(&self, line: u32) -> u32 {
+        let (Some(&start), Some(&next_start)) = (
+            self.line_starts.get(line as usize),
+            self.line_starts.get(line as usize + 1),
+        ) else {
+            return 0;
+        };
+
+        // Line breaks only consist of ASCII characters
+        (next_start - start - self.line(line).len()) as u32
+    }
+
+    /// Returns the position at the end of the given line, before the line break.
+    pub fn line_end(&self, line: u32) -> Position {
+        let char_len = self.line(line).chars().count() as u32;
+        Position::new(line, self.encoded_column(line, char_len))
+    }
+
+    /// Converts a column counted in characters into a column of the negotiated encoding.
+    ///
+    /// Columns after the line end are counted as one code unit per character.
+    pub fn encoded_column(&self, line: u32, char_column: u32) -> u32 {
+        let line = self.line(line);
+        let char_column = char_column as usize;
+
+        let encoded_len: usize = line
+            .chars()
+            .take(char_column)
+            .map(|c| self.encoding.char_len(c))
+            .sum();
+        let chars_after_end = char_column.saturating_sub(line.chars().count());
+
+        (encoded_len + chars_after_end) as u32
+    }
+
+    /// Converts a column of the negotiated encoding into a column counted in characters.
+    ///
+    /// Columns inside a character are moved behind it. Columns after the line end
+    /// are counted as one code unit per character.
+    pub fn char_column(&self, line: u32, encoded_column: u32) -> u32 {
+        let encoded_column = encoded_column as usize;
+        let mut encoded_len = 0;
+        let mut char_column = 0;
+
+        for c in self.line(line).chars() {
+            if encoded_len >= encoded_column {
+                return char_column as u32;
+            }
+            encoded_len += self.encoding.char_len(c);
+            char_column += 1;
+        }
+
+        (char_column + encoded_column.saturating_sub(encoded_len)) as u32
+    }
+
+    /// Converts the length of a text starting at the given character column
+    /// into the length in the negotiated encoding.
+    ///
+    /// Texts longer than the rest of the line continue on the next lines,
+    /// so each line is encoded on its own and line breaks count with their length in the text.
+    pub fn encoded_len(&self, line: u32, char_column: u32, char_len: u32) -> u32 {
+        let mut line = line;
+        let mut char_column = char_column;
+        let mut remaining = char_len;
+        let mut encoded_len = 0;
+
+        loop {
+            let line_len = self.line(line).chars().count() as u32;
+            let available = line_len.saturating_sub(char_column);
+            let line_break_len = self.line_break_len(line);
+
+            if remaining <= available || line_break_len == 0 {
+                return encoded_len + self.encoded_column(line, char_column + remaining)
+                    - self.encoded_column(line, char_column);
+            }
+
+            encoded_len += self
+                .encoded_column(line, line_len)
+                .saturating_sub(self.encoded_column(line, char_column))
+                + line_break_len;
+            remaining = remaining.saturating_sub(available + line_break_len);
+            line += 1;
+            char_column = 0;
+        }
+    }
+}
+
+/// Converts an LSP position in the given encoding into a byte offset in the given text.
+///
+/// Positions after the end of a line are clamped to the line end,
+/// and positions after the last line are clamped to the text end.
+pub fn offset_at(text: &str, position: Position, encoding: PositionEncoding) -> usize {
+    let mut line_start = 0;
+
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(newline) => line_start += newline + 1,
+            None => return text.len(),
+        }
+    }
+
+    let line = &text[line_start..];
+    let line_end = line.find('\n').unwrap_or(line.len());
+    let line = line[..line_end]
+        .strip_suffix('\r')
+        .unwrap_or(&line[..line_end]);
+
+    let mut encoded_column = 0;
+    for (byte_offset, c) in line.char_indices() {
+        if encoded_column >= position.character as usize {
+            return line_start + byte_offset;
+        }
+        encoded_column += encoding.char_len(c);
+    }
+
+    line_start + line.len()
+}
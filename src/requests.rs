@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use lsp_server::{RequestId, Response};
+use lsp_types::request::{
+    RegisterCapability, Request, SemanticTokensRefresh, WorkspaceConfiguration,
+    WorkspaceDiagnosticRefresh,
+};
+use tokio::time::Instant;
+
+/// Minimal time between two refresh requests of the same kind.
+pub const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Time after which an unanswered refresh request no longer blocks new refreshes.
+///
+/// Prevents that a client which never answers stops all further refreshes.
+pub const REFRESH_RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Requests the server sends to the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerRequest {
+    RegisterCapability,
+    Configuration,
+    SemanticTokensRefresh,
+    DiagnosticRefresh,
+}
+
+impl ServerRequest {
+    pub fn method(&self) -> &'static str {
+        match self {
+            ServerRequest::RegisterCapability => RegisterCapability::METHOD,
+            ServerRequest::Configuration => WorkspaceConfiguration::METHOD,
+            ServerRequest::SemanticTokensRefresh => SemanticTokensRefresh::METHOD,
+            ServerRequest::DiagnosticRefresh => WorkspaceDiagnosticRefresh::METHOD,
+        }
+    }
+
+    /// Prefix of the ids of sent requests, to make them recognizable in client logs.
+    fn id_prefix(&self) -> &'static str {
+        match self {
+            ServerRequest::RegisterCapability => "register-capabilities",
+            ServerRequest::Configuration => "config",
+            ServerRequest::SemanticTokensRefresh => "semantic-tokens-refresh",
+            ServerRequest::DiagnosticRefresh => "diagnostic-refresh",
+        }
+    }
+}
+
+/// Keeps track of requests sent to the client that are not yet answered.
+#[derive(Debug, Default)]
+pub struct PendingRequests {
+    request_cnt: usize,
+    pending: HashMap<RequestId, ServerRequest>,
+}
+
+impl PendingRequests {
+    /// Creates a request with a new id, and marks it as pending until its response arrives.
+    pub fn create(
+        &mut self,
+        request: ServerRequest,
+        params: serde_json::Value,
+    ) -> lsp_server::Request {
+        self.request_cnt += 1;
+        let id: RequestId = format!("{}-{}", request.id_prefix(), self.request_cnt).into();
+
+        self.pending.insert(id.clone(), request);
+
+        lsp_server::Request {
+            id,
+            method: request.method().to_string(),
+            params,
+        }
+    }
+
+    /// Marks the request of the given response as answered.
+    ///
+    /// Returns the answered request, or `None` if no request with the response id is pending.
+    /// Error responses are reported, but still complete their request.
+    pub fn complete(&mut self, response: &Response) -> Option<ServerRequest> {
+        let request = self.pending.remove(&response.id)?;
+
+        if let Some(error) = &response.error {
+            eprintln!(
+                "Request '{}' failed with code {}: {}",
+                request.method(),
+                error.code,
+                error.message
+            );
+        }
+
+        Some(request)
+    }
+}
+
+/// Decides when the client should be asked to refresh its semantic tokens or diagnostics.
+///
+/// Refreshes requested while a refresh is unanswered, or within the [`REFRESH_INTERVAL`]
+/// of the last refresh, are combined into one refresh that is sent afterwards.
+#[derive(Debug)]
+pub struct RefreshThrottle {
+    interval: Duration,
+    /// `true` if documents changed since the last refresh was sent.
+    requested: bool,
+    /// Instant the last refresh was sent at.
+    last_sent: Option<Instant>,
+    /// `true` while the last sent refresh is not answered.
+    unanswered: bool,
+}
+
+impl Default for RefreshThrottle {
+    fn default() -> Self {
+        RefreshThrottle::new(REFRESH_INTERVAL)
+    }
+}
+
+impl RefreshThrottle {
+    pub fn new(interval: Duration) -> Self {
+        RefreshThrottle {
+            interval,
+            requested: false,
+            last_sent: None,
+            unanswered: false,
+        }
+    }
+
+    /// Marks that the refreshed content of a document changed.
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    /// Marks that the client answered the last refresh.
+    pub fn answered(&mut self) {
+        self.unanswered = false;
+    }
+
+    /// Marks that a refresh was sent at the given instant.
+    pub fn sent(&mut self, now: Instant) {
+        self.requested = false;
+        self.unanswered = true;
+        self.last_sent = Some(now);
+    }
+
+    /// Returns the instant the next refresh is due at, or `None` if no refresh is requested.
+    ///
+    /// While the last refresh is unanswered, the next one waits for the response timeout.
+    pub fn next_refresh(&self, now: Instant) -> Option<Instant> {
+        if !self.requested {
+            return None;
+        }
+
+        let Some(last_sent) = self.last_sent else {
+            return Some(now);
+        };
+
+        let due = if self.unanswered {
+            last_sent + REFRESH_RESPONSE_TIMEOUT
+        } else {
+            last_sent + self.interval
+        };
+
+        Some(due.max(now))
+    }
+}
@@ -0,0 +1,351 @@
+use lsp_server::{RequestId, Response};
+use lsp_types::{DocumentSymbol, Position, Range, SelectionRange, SelectionRangeParams};
+use unimarkup_core::{document::Document, elements::blocks::Block};
+use unimarkup_inline::Inline;
+
+use crate::blocks::{block_lines, entry_columns, entry_end_line, heading_content_column};
+use crate::doc_sync::VersionedDocument;
+use crate::document_symbols::get_document_symbols;
+use crate::line_index::LineIndex;
+
+/// Answers a `textDocument/selectionRange` request with one selection range per position.
+pub(crate) fn get_selection_ranges_response(
+    id: RequestId,
+    params: SelectionRangeParams,
+    document: Option<&VersionedDocument>,
+) -> Response {
+    let ranges: Vec<SelectionRange> = match document {
+        Some(um_doc) => params
+            .positions
+            .iter()
+            .map(|position| get_selection_range(&um_doc.doc, &um_doc.line_index, *position))
+            .collect(),
+        None => vec![],
+    };
+
+    let result = serde_json::to_value(&ranges).unwrap();
+    Response {
+        id,
+        result: Some(result),
+        error: None,
+    }
+}
+
+/// Creates the selection range at the given position, whose parents grow along the document structure.
+///
+/// Selections grow from the word at the position to the content and then the delimiters
+/// of each enclosing inline, the enclosing blocks, the enclosing heading sections,
+/// and finally the whole document.
+pub fn get_selection_range(
+    document: &Document,
+    line_index: &LineIndex,
+    position: Position,
+) -> SelectionRange {
+    let cursor = CharPosition {
+        line: position.line,
+        column: line_index.char_column(position.line, position.character),
+    };
+
+    // Ranges from the outermost to the innermost
+    let last_line = line_index.line_count().saturating_sub(1);
+    let mut ranges = vec![Range::new(
+        Position::new(0, 0),
+        line_index.line_end(last_line),
+    )];
+
+    push_section_ranges(
+        &mut ranges,
+        &get_document_symbols(document, line_index),
+        position,
+    );
+    push_block_ranges(&mut ranges, &document.blocks, line_index, cursor);
+
+    if let Some(word_range) = word_range(line_index, cursor) {
+        ranges.push(word_range);
+    }
+
+    let mut selection_range: Option<SelectionRange> = None;
+    for range in ranges {
+        if let Some(parent) = &selection_range {
+            // Skip ranges that would not grow the selection
+            if range == parent.range || !contains(&parent.range, &range) {
+                continue;
+            }
+        }
+
+        selection_range = Some(SelectionRange {
+            range,
+            parent: selection_range.map(Box::new),
+        });
+    }
+
+    selection_range.expect("The document range is always added.")
+}
+
+/// Position with the column counted in characters, starting at 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct CharPosition {
+    line: u32,
+    column: u32,
+}
+
+impl CharPosition {
+    /// Converts a Unimarkup position, which starts at 1.
+    fn from_um(line: usize, column: usize) -> Self {
+        CharPosition {
+            line: line.saturating_sub(1) as u32,
+            column: column.saturating_sub(1) as u32,
+        }
+    }
+
+    fn encode(&self, line_index: &LineIndex) -> Position {
+        Position::new(self.line, line_index.encoded_column(self.line, self.column))
+    }
+}
+
+/// Shifts the inline columns of one line, whose inline positions do not start at the line start.
+#[derive(Debug, Default, Clone, Copy)]
+struct ColumnOffset {
+    line: u32,
+    columns: u32,
+}
+
+impl ColumnOffset {
+    fn apply(&self, position: CharPosition) -> CharPosition {
+        if position.line != self.line {
+            return position;
+        }
+
+        CharPosition {
+            line: position.line,
+            column: position.column + self.columns,
+        }
+    }
+}
+
+fn contains(outer: &Range, inner: &Range) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+/// Adds the ranges of all heading sections that contain the position.
+fn push_section_ranges(ranges: &mut Vec<Range>, symbols: &[DocumentSymbol], position: Position) {
+    let section = symbols
+        .iter()
+        .find(|symbol| symbol.range.start <= position && position <= symbol.range.end);
+
+    if let Some(section) = section {
+        ranges.push(section.range);
+
+        if let Some(children) = &section.children {
+            push_section_ranges(ranges, children, position);
+        }
+    }
+}
+
+/// Adds the ranges of the block containing the cursor and of all inlines inside it.
+fn push_block_ranges(
+    ranges: &mut Vec<Range>,
+    blocks: &[Block],
+    line_index: &LineIndex,
+    cursor: CharPosition,
+) {
+    // Unimarkup lines start at 1
+    let cursor_line = cursor.line as usize + 1;
+
+    for block in blocks {
+        let Some(lines) = block_lines(block) else {
+            continue;
+        };
+        if !lines.contains(&cursor_line) {
+            continue;
+        }
+
+        ranges.push(lines_range(line_index, *lines.start(), *lines.end()));
+
+        match block {
+            Block::Heading(heading) => {
+                // Inline columns of the heading line are relative to the heading content
+                let offset = ColumnOffset {
+                    line: heading.line_nr.saturating_sub(1) as u32,
+                    columns: heading_content_column(heading, line_index) as u32,
+                };
+
+                push_inline_ranges(ranges, heading.content.iter(), line_index, cursor, offset);
+            }
+            Block::Paragraph(paragraph) => push_inline_ranges(
+                ranges,
+                paragraph.content.iter(),
+                line_index,
+                cursor,
+                ColumnOffset::default(),
+            ),
+            Block::Verbatim(_) => {
+                // Content lines are enclosed by the opening and closing fence
+                let content_lines = lines.start() + 1..*lines.end();
+                if content_lines.contains(&cursor_line) {
+                    ranges.push(lines_range(
+                        line_index,
+                        content_lines.start,
+                        content_lines.end - 1,
+                    ));
+                }
+            }
+            Block::BulletList(bullet_list) => {
+                let entry = bullet_list
+                    .entries
+                    .iter()
+                    .find(|entry| (entry.line_nr..=entry_end_line(entry)).contains(&cursor_line));
+
+                if let Some(entry) = entry {
+                    ranges.push(lines_range(
+                        line_index,
+                        entry.line_nr,
+                        entry_end_line(entry),
+                    ));
+                    // Inline columns of the entry line start after the keyword and the following whitespace
+                    let offset = ColumnOffset {
+                        line: entry.line_nr.saturating_sub(1) as u32,
+                        columns: entry_columns(entry, line_index).content as u32,
+                    };
+
+                    push_inline_ranges(ranges, entry.heading.iter(), line_index, cursor, offset);
+                    push_block_ranges(ranges, &entry.body, line_index, cursor);
+                }
+            }
+            // Unreachable as long as the core has no other blocks
+            #[allow(unreachable_patterns)]
+            _ => {}
+        }
+
+        return;
+    }
+}
+
+/// Adds the ranges of the delimiters and content of all inlines that contain the cursor.
+fn push_inline_ranges<'a>(
+    ranges: &mut Vec<Range>,
+    inlines: impl Iterator<Item = &'a Inline>,
+    line_index: &LineIndex,
+    cursor: CharPosition,
+    offset: ColumnOffset,
+) {
+    for inline in inlines {
+        let span = inline.span();
+        let start = offset.apply(CharPosition::from_um(
+            span.start().line,
+            span.start().column,
+        ));
+        // Span ends include the last character
+        let end = offset.apply(CharPosition::from_um(
+            span.end().line,
+            span.end().column + 1,
+        ));
+
+        if cursor < start || end < cursor {
+            continue;
+        }
+
+        let nested = match inline {
+            Inline::Bold(nested)
+            | Inline::Italic(nested)
+            | Inline::Underline(nested)
+            | Inline::Subscript(nested)
+            | Inline::Superscript(nested)
+            | Inline::Overline(nested)
+            | Inline::Strikethrough(nested)
+            | Inline::Highlight(nested)
+            | Inline::Quote(nested)
+            | Inline::TextGroup(nested)
+            | Inline::Attributes(nested)
+            | Inline::Substitution(nested)
+            | Inline::Parentheses(nested) => Some(nested),
+            Inline::Multiple(nested) => {
+                // Only groups other inlines, so it has no own selection
+                push_inline_ranges(ranges, nested.iter(), line_index, cursor, offset);
+                return;
+            }
+            Inline::Verbatim(_) | Inline::Math(_) => None,
+            Inline::Plain(_)
+            | Inline::Whitespace(_)
+            | Inline::Newline(_)
+            | Inline::EndOfLine(_) => {
+                continue;
+            }
+        };
+
+        let delimiters = inline.delimiters();
+        let open_len = delimiters.open().as_str().chars().count() as u32;
+        let close_len = delimiters
+            .close()
+            .map_or(0, |close| close.as_str().chars().count() as u32);
+
+        let content_start = CharPosition {
+            line: start.line,
+            column: start.column + open_len,
+        };
+        let content_end = CharPosition {
+            line: end.line,
+            column: end.column.saturating_sub(close_len),
+        };
+
+        ranges.push(Range::new(start.encode(line_index), end.encode(line_index)));
+        if content_start <= content_end {
+            ranges.push(Range::new(
+                content_start.encode(line_index),
+                content_end.encode(line_index),
+            ));
+        }
+
+        if let Some(nested) = nested {
+            push_inline_ranges(ranges, nested.iter(), line_index, cursor, offset);
+        }
+
+        return;
+    }
+}
+
+/// Returns the range of the word at or directly before the cursor.
+///
+/// Words consist of alphanumeric characters and underscores.
+fn word_range(line_index: &LineIndex, cursor: CharPosition) -> Option<Range> {
+    let is_word_char = |c: &char| c.is_alphanumeric() || *c == '_';
+    let chars: Vec<char> = line_index.line(cursor.line).chars().collect();
+    let column = (cursor.column as usize).min(chars.len());
+
+    let start = column
+        - chars[..column]
+            .iter()
+            .rev()
+            .take_while(|c| is_word_char(c))
+            .count();
+    let end = column
+        + chars[column..]
+            .iter()
+            .take_while(|c| is_word_char(c))
+            .count();
+
+    if start == end {
+        return None;
+    }
+
+    Some(Range::new(
+        CharPosition {
+            line: cursor.line,
+            column: start as u32,
+        }
+        .encode(line_index),
+        CharPosition {
+            line: cursor.line,
+            column: end as u32,
+        }
+        .encode(line_index),
+    ))
+}
+
+/// Returns the range of complete lines, where lines start at 1 like all Unimarkup positions.
+fn lines_range(line_index: &LineIndex, start_line: usize, end_line: usize) -> Range {
+    Range::new(
+        Position::new(start_line.saturating_sub(1) as u32, 0),
+        line_index.line_end(end_line.saturating_sub(1) as u32),
+    )
+}
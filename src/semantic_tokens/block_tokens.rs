@@ -2,70 +2,71 @@ use lsp_types::SemanticToken;
 use unimarkup_core::{
     document::Document,
     elements::blocks::Block,
-    elements::{atomic::Heading, atomic::Paragraph, enclosed::Verbatim},
+    elements::{
+        atomic::Heading,
+        atomic::Paragraph,
+        enclosed::Verbatim,
+        indents::{BulletList, BulletListEntry},
+    },
 };
 
-use super::{inline_tokens::SemanticInlineTokenizer, TokenValue};
-
-#[derive(Default, Debug, Clone)]
-pub(crate) enum TokenType {
-    #[default]
-    Paragraph,
-    Heading,
-    Verbatim,
-}
-
-impl TokenValue for TokenType {
-    fn value(&self) -> u32 {
-        match self {
-            TokenType::Paragraph => 2, //21,
-            TokenType::Heading => 3,
-            TokenType::Verbatim => 18,
-        }
-    }
-}
+use super::{
+    embedded_tokens::embedded_tokens, inline_tokens::inlines_tokens, legend::TokenType, TokenValue,
+};
+use crate::blocks::{entry_columns, heading_content_column};
+use crate::line_index::LineIndex;
 
 pub(crate) trait SemanticBlockTokenizer {
-    fn tokens(&self) -> Vec<SemanticToken>;
+    fn tokens(&self, line_index: &LineIndex) -> Vec<SemanticToken>;
 }
 
 impl SemanticBlockTokenizer for Document {
-    fn tokens(&self) -> Vec<SemanticToken> {
+    fn tokens(&self, line_index: &LineIndex) -> Vec<SemanticToken> {
         let mut tokens = Vec::<SemanticToken>::new();
         for block in &self.blocks {
-            tokens.append(&mut block.tokens());
+            tokens.append(&mut block.tokens(line_index));
         }
         tokens
     }
 }
 
 impl SemanticBlockTokenizer for Block {
-    fn tokens(&self) -> Vec<SemanticToken> {
+    fn tokens(&self, line_index: &LineIndex) -> Vec<SemanticToken> {
         match self {
-            Block::Heading(heading) => heading.tokens(),
-            Block::Paragraph(paragraph) => paragraph.tokens(),
-            Block::Verbatim(verbatim) => verbatim.tokens(),
-            _ => todo!(),
+            Block::Heading(heading) => heading.tokens(line_index),
+            Block::Paragraph(paragraph) => paragraph.tokens(line_index),
+            Block::Verbatim(verbatim) => verbatim.tokens(line_index),
+            Block::BulletList(bullet_list) => bullet_list.tokens(line_index),
+            // Blocks that are not supported yet are left to the default highlighting.
+            // Unreachable as long as the core has no other blocks.
+            #[allow(unreachable_patterns)]
+            _ => vec![],
         }
     }
 }
 
 impl SemanticBlockTokenizer for Heading {
-    fn tokens(&self) -> Vec<SemanticToken> {
+    fn tokens(&self, line_index: &LineIndex) -> Vec<SemanticToken> {
+        let level: u32 = u8::from(self.level).into();
+
         let mut tokens = vec![SemanticToken {
             delta_line: self.line_nr as u32,
             delta_start: 1,
-            length: (u8::from(self.level)).into(),
+            length: level,
             token_type: TokenType::Heading.value(),
             token_modifiers_bitset: 0,
         }];
 
-        tokens.append(
-            &mut self
-                .content
-                .iter()
-                .flat_map(|inline| inline.tokens(&mut vec![]))
-                .collect(),
+        let content_offset = heading_content_column(self, line_index) as u32;
+        tokens.extend(
+            inlines_tokens(&self.content, &mut vec![])
+                .into_iter()
+                .map(|mut token| {
+                    if token.delta_line == self.line_nr as u32 {
+                        token.delta_start += content_offset;
+                    }
+                    token
+                }),
         );
 
         tokens
@@ -73,48 +74,162 @@ impl SemanticBlockTokenizer for Heading {
 }
 
 impl SemanticBlockTokenizer for Paragraph {
-    fn tokens(&self) -> Vec<SemanticToken> {
-        self.content
+    fn tokens(&self, _line_index: &LineIndex) -> Vec<SemanticToken> {
+        inlines_tokens(&self.content, &mut vec![])
+    }
+}
+
+impl SemanticBlockTokenizer for BulletList {
+    fn tokens(&self, line_index: &LineIndex) -> Vec<SemanticToken> {
+        self.entries
             .iter()
-            .flat_map(|inline| inline.tokens(&mut vec![]))
+            .flat_map(|entry| entry.tokens(line_index))
             .collect()
     }
 }
 
-impl SemanticBlockTokenizer for Verbatim {
-    fn tokens(&self) -> Vec<SemanticToken> {
-        //TODO: Change length after Verbatim contains needed information
+impl SemanticBlockTokenizer for BulletListEntry {
+    fn tokens(&self, line_index: &LineIndex) -> Vec<SemanticToken> {
+        let columns = entry_columns(self, line_index);
+
+        // The entry keyword is always one of `-`, `+` or `*`
         let mut tokens = vec![SemanticToken {
             delta_line: self.line_nr as u32,
-            delta_start: 1,
-            length: 50,
-            token_type: TokenType::Verbatim.value(),
+            delta_start: (columns.keyword + 1) as u32,
+            length: 1,
+            token_type: TokenType::Delimiter.value(),
             token_modifiers_bitset: 0,
         }];
 
-        let lines = self.content.lines();
-        for (i, line) in lines.enumerate() {
-            tokens.push(SemanticToken {
-                delta_line: (self.line_nr + i + 1) as u32,
-                delta_start: 1,
-                length: (line.len() as u32),
-                token_type: TokenType::Verbatim.value(),
-                token_modifiers_bitset: 0,
-            });
+        tokens.extend(
+            inlines_tokens(&self.heading, &mut vec![])
+                .into_iter()
+                .map(|mut token| {
+                    if token.delta_line == self.line_nr as u32 {
+                        token.delta_start += columns.content as u32;
+                    }
+                    token
+                }),
+        );
+
+        for block in &self.body {
+            tokens.append(&mut block.tokens(line_index));
         }
 
-        tokens.push(SemanticToken {
-            delta_line: (self.line_nr + self.content.lines().count() + 1) as u32,
-            delta_start: 1,
-            length: 50,
-            token_type: TokenType::Verbatim.value(),
-            token_modifiers_bitset: 0,
+        tokens
+    }
+}
+
+impl SemanticBlockTokenizer for Verbatim {
+    fn tokens(&self, line_index: &LineIndex) -> Vec<SemanticToken> {
+        let content_line_cnt = self.content.lines().count();
+        let opening_line = self.line_nr;
+        let closing_line = self.line_nr + content_line_cnt + 1;
+
+        let mut tokens = Vec::new();
+
+        if let Some(opening_fence) = fence(line_index, opening_line) {
+            tokens.push(opening_fence.token(opening_line));
+
+            if let Some(language) = &self.data_lang {
+                let fence_end = opening_fence.start_column + opening_fence.length;
+                let line = line_index.line(opening_line as u32 - 1);
+
+                // The language identifier follows the fence, optionally separated by whitespace
+                let language_column = line
+                    .chars()
+                    .skip(fence_end)
+                    .take_while(|c| c.is_whitespace())
+                    .count()
+                    + fence_end;
+
+                tokens.push(SemanticToken {
+                    delta_line: opening_line as u32,
+                    delta_start: (language_column + 1) as u32,
+                    length: language.chars().count() as u32,
+                    token_type: TokenType::Language.value(),
+                    token_modifiers_bitset: 0,
+                });
+            }
+        }
+
+        // Content lines with their line number and the column their content starts at
+        let content_lines = ((opening_line + 1)..closing_line).map(|line_nr| {
+            let line = line_index.line(line_nr as u32 - 1);
+            let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+            let content = line.trim_start();
+
+            (line_nr, indent, content)
         });
 
+        let highlighted = self
+            .data_lang
+            .as_ref()
+            .and_then(|language| embedded_tokens(language, content_lines.clone()));
+
+        match highlighted {
+            Some(mut highlighted_tokens) => tokens.append(&mut highlighted_tokens),
+            None => {
+                for (line_nr, indent, content) in content_lines {
+                    if !content.is_empty() {
+                        tokens.push(SemanticToken {
+                            delta_line: line_nr as u32,
+                            delta_start: (indent + 1) as u32,
+                            length: content.chars().count() as u32,
+                            token_type: TokenType::Verbatim.value(),
+                            token_modifiers_bitset: 0,
+                        });
+                    }
+                }
+            }
+        }
+
+        if let Some(closing_fence) = fence(line_index, closing_line) {
+            tokens.push(closing_fence.token(closing_line));
+        }
+
         tokens
     }
 }
 
+/// Backtick run that opens or closes a verbatim block.
+struct Fence {
+    /// Column the fence starts at, counted in characters starting at 0.
+    start_column: usize,
+    /// Number of backticks.
+    length: usize,
+}
+
+impl Fence {
+    fn token(&self, line_nr: usize) -> SemanticToken {
+        SemanticToken {
+            delta_line: line_nr as u32,
+            delta_start: (self.start_column + 1) as u32,
+            length: self.length as u32,
+            token_type: TokenType::Delimiter.value(),
+            token_modifiers_bitset: 0,
+        }
+    }
+}
+
+/// Finds the fence of a verbatim block at the given line.
+///
+/// Leading whitespace is skipped, so fences of indented and nested blocks are found as well.
+fn fence(line_index: &LineIndex, line_nr: usize) -> Option<Fence> {
+    let line = line_index.line(line_nr.checked_sub(1)? as u32);
+    let start_column = line.chars().take_while(|c| c.is_whitespace()).count();
+    let length = line
+        .chars()
+        .skip(start_column)
+        .take_while(|c| *c == '`')
+        .count();
+
+    (length > 0).then_some(Fence {
+        start_column,
+        length,
+    })
+}
+
 // fn calculate_column_offset(open_types: &mut [OpenTokenType]) -> u32 {
 //     match open_types.last() {
 //         Some(last_open) => {
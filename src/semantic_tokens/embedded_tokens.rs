@@ -0,0 +1,184 @@
+use std::sync::OnceLock;
+
+use lsp_types::SemanticToken;
+use syntect::parsing::{
+    ParseState, Scope, ScopeStack, SyntaxDefinition, SyntaxReference, SyntaxSet,
+};
+
+use super::{legend::TokenType, TokenValue};
+
+/// Maps TextMate scopes to token types.
+///
+/// The first scope that is a prefix of a highlighted scope is taken,
+/// so more specific scopes must come before their parent scopes.
+const SCOPE_TOKEN_TYPES: [(&str, TokenType); 16] = [
+    ("comment", TokenType::Comment),
+    ("string", TokenType::String),
+    ("constant.numeric", TokenType::Number),
+    ("constant.language", TokenType::Keyword),
+    ("keyword.operator", TokenType::Operator),
+    ("keyword", TokenType::Keyword),
+    ("storage.type", TokenType::Keyword),
+    ("storage.modifier", TokenType::Keyword),
+    ("entity.name.function", TokenType::Function),
+    ("support.function", TokenType::Function),
+    ("variable.function", TokenType::Function),
+    ("entity.name", TokenType::Type),
+    ("support.type", TokenType::Type),
+    ("support.class", TokenType::Type),
+    ("variable.parameter", TokenType::Parameter),
+    ("variable", TokenType::Variable),
+];
+
+/// Grammars that are not part of the default syntaxes of the highlighter.
+const EXTRA_SYNTAXES: [&str; 1] = [include_str!("../../syntaxes/TOML.sublime-syntax")];
+
+/// Names of verbatim languages that differ from the syntax names of the highlighter.
+const LANGUAGE_ALIASES: [(&str, &str); 3] = [("shell", "sh"), ("console", "sh"), ("py", "python")];
+
+struct Highlighter {
+    syntax_set: SyntaxSet,
+    scope_types: Vec<(Scope, TokenType)>,
+}
+
+/// The highlighter is loaded on first use, because loading all syntaxes takes some time.
+fn highlighter() -> &'static Highlighter {
+    static HIGHLIGHTER: OnceLock<Highlighter> = OnceLock::new();
+
+    HIGHLIGHTER.get_or_init(|| {
+        let mut syntax_builder = SyntaxSet::load_defaults_newlines().into_builder();
+        for extra_syntax in EXTRA_SYNTAXES {
+            match SyntaxDefinition::load_from_str(extra_syntax, true, None) {
+                Ok(syntax) => syntax_builder.add(syntax),
+                Err(err) => eprintln!("Invalid embedded syntax: {}", err),
+            }
+        }
+
+        Highlighter {
+            syntax_set: syntax_builder.build(),
+            scope_types: SCOPE_TOKEN_TYPES
+                .iter()
+                .filter_map(|(scope, token_type)| Some((Scope::new(scope).ok()?, *token_type)))
+                .collect(),
+        }
+    })
+}
+
+fn find_syntax<'a>(syntax_set: &'a SyntaxSet, language: &str) -> Option<&'a SyntaxReference> {
+    let language = language.trim().to_ascii_lowercase();
+    let language = LANGUAGE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == language)
+        .map_or(language.as_str(), |(_, name)| name);
+
+    syntax_set.find_syntax_by_token(language)
+}
+
+/// Highlights the content lines of a verbatim block with the grammar of the given language.
+///
+/// Lines are given with their line number and the character column their content starts at.
+/// Returns `None` if no grammar exists for the language,
+/// or the content could not be parsed with it.
+pub(crate) fn embedded_tokens<'a>(
+    language: &str,
+    lines: impl Iterator<Item = (usize, usize, &'a str)>,
+) -> Option<Vec<SemanticToken>> {
+    let highlighter = highlighter();
+    let syntax = find_syntax(&highlighter.syntax_set, language)?;
+
+    let mut parse_state = ParseState::new(syntax);
+    let mut scope_stack = ScopeStack::new();
+    let mut tokens = Vec::new();
+
+    for (line_nr, start_column, line) in lines {
+        // Syntaxes are loaded for lines that end with a newline
+        let line = format!("{}\n", line);
+        let ops = parse_state
+            .parse_line(&line, &highlighter.syntax_set)
+            .ok()?;
+
+        let mut segment_start = 0;
+        for (op_offset, op) in ops.iter().map(|(offset, op)| (*offset, Some(op))).chain(
+            // Closes the segment up to the line end
+            std::iter::once((line.len() - 1, None)),
+        ) {
+            let segment_end = op_offset.min(line.len() - 1);
+
+            if segment_end > segment_start {
+                let segment = &line[segment_start..segment_end];
+                let token_type = highlighter.token_type(&scope_stack);
+
+                // Whitespace is only part of highlighted code like strings and comments
+                let content = match token_type {
+                    TokenType::Verbatim => segment.trim(),
+                    _ => segment,
+                };
+
+                if !content.trim().is_empty() {
+                    let leading_whitespace = segment.len() - segment.trim_start().len();
+                    let content_start = match token_type {
+                        TokenType::Verbatim => segment_start + leading_whitespace,
+                        _ => segment_start,
+                    };
+                    let char_column = line[..content_start].chars().count();
+
+                    push_merged(
+                        &mut tokens,
+                        SemanticToken {
+                            delta_line: line_nr as u32,
+                            delta_start: (start_column + char_column + 1) as u32,
+                            length: content.chars().count() as u32,
+                            token_type: token_type.value(),
+                            token_modifiers_bitset: 0,
+                        },
+                    );
+                }
+
+                segment_start = segment_end;
+            }
+
+            if let Some(op) = op {
+                scope_stack.apply(op).ok()?;
+            }
+        }
+    }
+
+    Some(tokens)
+}
+
+/// Adds the token, or extends the last token if it directly precedes the token with the same type.
+///
+/// Grammars split strings and comments at their punctuation,
+/// but they should be highlighted as one token.
+fn push_merged(tokens: &mut Vec<SemanticToken>, token: SemanticToken) {
+    if let Some(last) = tokens.last_mut() {
+        if last.delta_line == token.delta_line
+            && last.token_type == token.token_type
+            && last.delta_start + last.length == token.delta_start
+        {
+            last.length += token.length;
+            return;
+        }
+    }
+
+    tokens.push(token);
+}
+
+impl Highlighter {
+    /// Returns the token type of the innermost scope that is mapped to a token type.
+    ///
+    /// Code without a mapped scope keeps the [`TokenType::Verbatim`] type.
+    fn token_type(&self, scope_stack: &ScopeStack) -> TokenType {
+        scope_stack
+            .as_slice()
+            .iter()
+            .rev()
+            .find_map(|scope| {
+                self.scope_types
+                    .iter()
+                    .find(|(mapped_scope, _)| mapped_scope.is_prefix_of(*scope))
+                    .map(|(_, token_type)| *token_type)
+            })
+            .unwrap_or(TokenType::Verbatim)
+    }
+}
@@ -1,12 +1,17 @@
 use lsp_types::SemanticToken;
-use unimarkup_inline::{Inline, NestedContent, TokenDelimiters, TokenKind};
+use unimarkup_inline::{Inline, NestedContent, TokenDelimiters};
 
-use super::{block_tokens::TokenType, TokenValue};
+use super::{
+    legend::{TokenModifier, TokenType},
+    TokenValue,
+};
 
 #[derive(Debug, Default, Clone)]
 pub(crate) struct OpenTokenModifier {
     /// The open token modifier
     token_modifier: TokenModifier,
+    /// Token type of the content inside the open inline
+    token_type: TokenType,
     /// Column start of the modifier
     _start_column: u32,
     /// Column offset the content of this modifier starts.
@@ -16,158 +21,196 @@ pub(crate) struct OpenTokenModifier {
     _start_column_offset: u32,
 }
 
-#[derive(Default, Debug, Clone)]
-pub(crate) enum TokenModifier {
-    #[default]
-    Plain,
-    Bold,
-    Italic,
-}
-
-impl TokenValue for TokenModifier {
-    fn value(&self) -> u32 {
-        // Note: These values must set the correct modifier bit
-        match self {
-            TokenModifier::Plain => 0,
-            TokenModifier::Bold => 1,
-            TokenModifier::Italic => 1 << 1,
-        }
-    }
-}
-
 pub(crate) trait SemanticInlineTokenizer {
     fn tokens(&self, open_modifiers: &mut Vec<OpenTokenModifier>) -> Vec<SemanticToken>;
 }
 
 impl SemanticInlineTokenizer for NestedContent {
     fn tokens(&self, open_modifiers: &mut Vec<OpenTokenModifier>) -> Vec<SemanticToken> {
-        self.iter()
-            .flat_map(|inline| inline.tokens(open_modifiers))
-            .collect()
+        inlines_tokens(self.iter(), open_modifiers)
     }
 }
 
+/// Creates the tokens of consecutive inlines.
+///
+/// The parser has no link inline, so parentheses directly following a text group
+/// are tokenized as the target of a link, e.g. `[text](target)`.
+pub(crate) fn inlines_tokens<'a>(
+    inlines: impl IntoIterator<Item = &'a Inline>,
+    open_modifiers: &mut Vec<OpenTokenModifier>,
+) -> Vec<SemanticToken> {
+    let mut tokens = Vec::new();
+    let mut inlines = inlines.into_iter().peekable();
+
+    while let Some(inline) = inlines.next() {
+        tokens.append(&mut inline.tokens(open_modifiers));
+
+        if !matches!(inline, Inline::TextGroup(_)) {
+            continue;
+        }
+
+        if let Some(link_target @ Inline::Parentheses(nested)) = inlines.peek() {
+            open_modifiers.push(OpenTokenModifier {
+                token_type: TokenType::Link,
+                ..Default::default()
+            });
+
+            let content_tokens = nested.tokens(open_modifiers);
+            tokens.append(&mut enclosed_tokens(
+                link_target,
+                content_tokens,
+                open_modifiers,
+            ));
+
+            open_modifiers.pop();
+            inlines.next();
+        }
+    }
+
+    tokens
+}
+
 impl SemanticInlineTokenizer for Inline {
     fn tokens(&self, open_modifiers: &mut Vec<OpenTokenModifier>) -> Vec<SemanticToken> {
         match self {
-            Inline::Bold(nested) | Inline::Italic(nested) => {
+            Inline::Bold(nested)
+            | Inline::Italic(nested)
+            | Inline::Underline(nested)
+            | Inline::Subscript(nested)
+            | Inline::Superscript(nested)
+            | Inline::Overline(nested)
+            | Inline::Strikethrough(nested)
+            | Inline::Highlight(nested)
+            | Inline::Quote(nested)
+            | Inline::Attributes(nested)
+            | Inline::Substitution(nested) => {
                 open_modifiers.push(self.into());
-                let delimiters = self.delimiters();
-
-                let mut tokens = vec![SemanticToken {
-                    delta_line: self.span().start().line as u32,
-                    delta_start: self.span().start().column as u32,
-                    length: delimiters.open().as_str().len() as u32,
-                    token_type: TokenType::default().value(),
-                    token_modifiers_bitset: get_modifier_bitfield(open_modifiers),
-                }];
 
-                tokens.append(&mut nested.tokens(open_modifiers));
-
-                let closing_delim = delimiters
-                    .close()
-                    .expect("Could not unwrap non-existent closing tag");
-
-                tokens.push(SemanticToken {
-                    delta_line: self.span().end().line as u32,
-                    delta_start: (self.span().end().column + 1 - closing_delim.as_str().len())
-                        as u32,
-                    length: closing_delim.as_str().len() as u32,
-                    token_type: TokenType::default().value(),
-                    token_modifiers_bitset: get_modifier_bitfield(open_modifiers),
-                });
+                let content_tokens = nested.tokens(open_modifiers);
+                let tokens = enclosed_tokens(self, content_tokens, open_modifiers);
 
                 open_modifiers.pop();
                 tokens
             }
-            Inline::Verbatim(plain_content) => {
-                vec![SemanticToken {
-                    delta_line: plain_content.span().start().line as u32,
-                    delta_start: plain_content.span().start().column as u32,
-                    length: (plain_content.content_len() + 2) as u32, // +2 for delimiters
-                    token_type: TokenType::Verbatim.value(),
+            Inline::TextGroup(nested) => {
+                let content_tokens = nested.tokens(open_modifiers);
+                enclosed_tokens(self, content_tokens, open_modifiers)
+            }
+            Inline::Parentheses(nested) | Inline::Multiple(nested) => nested.tokens(open_modifiers),
+            Inline::Verbatim(plain_content) | Inline::Math(plain_content) => {
+                let token_type = match self {
+                    Inline::Math(_) => TokenType::Math,
+                    _ => TokenType::Verbatim,
+                };
+                let open_delim_len = self.delimiters().open().as_str().chars().count();
+
+                let content_tokens = vec![SemanticToken {
+                    delta_line: self.span().start().line as u32,
+                    delta_start: (self.span().start().column + open_delim_len) as u32,
+                    length: plain_content.as_str().chars().count() as u32,
+                    token_type: token_type.value(),
                     token_modifiers_bitset: get_modifier_bitfield(open_modifiers),
-                }]
+                }];
+
+                enclosed_tokens(self, content_tokens, open_modifiers)
             }
-            Inline::Plain(plain_content) => {
+            Inline::Plain(plain_content) | Inline::Whitespace(plain_content) => {
                 if !open_modifiers.is_empty() {
                     vec![SemanticToken {
                         delta_line: plain_content.span().start().line as u32,
                         delta_start: plain_content.span().start().column as u32,
-                        length: plain_content.content_len() as u32,
-                        token_type: TokenType::default().value(),
+                        length: plain_content.as_str().chars().count() as u32,
+                        token_type: get_content_type(open_modifiers).value(),
                         token_modifiers_bitset: get_modifier_bitfield(open_modifiers),
                     }]
                 } else {
                     vec![]
                 }
             }
-            Inline::TextGroup(nested) => {
-                let delimiters: TokenDelimiters = self.delimiters();
-
-                let mut tokens = vec![SemanticToken {
-                    delta_line: nested.span().start().line as u32,
-                    delta_start: nested.span().start().column as u32,
-                    length: delimiters.open().as_str().len() as u32,
-                    token_type: TokenType::default().value(),
-                    token_modifiers_bitset: get_modifier_bitfield(open_modifiers),
-                }];
-
-                tokens.append(&mut nested.tokens(open_modifiers));
-
-                tokens.push(SemanticToken {
-                    delta_line: nested.span().end().line as u32,
-                    delta_start: (nested.span().end().column
-                        - delimiters
-                            .close()
-                            .unwrap_or(TokenKind::Plain)
-                            .as_str()
-                            .len()) as u32,
-                    length: delimiters
-                        .close()
-                        .unwrap_or(TokenKind::Plain)
-                        .as_str()
-                        .len() as u32,
-                    token_type: TokenType::default().value(),
-                    token_modifiers_bitset: get_modifier_bitfield(open_modifiers),
-                });
-
-                tokens
-            }
-            _ => vec![],
+            Inline::Newline(_) | Inline::EndOfLine(_) => vec![],
         }
     }
 }
 
 impl TokenValue for Inline {
     fn value(&self) -> u32 {
-        match self {
-            Inline::Bold(_) => TokenModifier::Bold.value(),
-            Inline::Italic(_) => TokenModifier::Italic.value(),
-            _ => TokenModifier::default().value(),
-        }
+        OpenTokenModifier::from(self).token_modifier.value()
     }
 }
 
 impl From<&Inline> for OpenTokenModifier {
     fn from(inline: &Inline) -> Self {
-        match inline {
-            Inline::Bold(_) => OpenTokenModifier {
-                token_modifier: TokenModifier::Bold,
-                ..Default::default()
-            },
-            Inline::Italic(_) => OpenTokenModifier {
-                token_modifier: TokenModifier::Italic,
-                ..Default::default()
-            },
-            _ => OpenTokenModifier {
-                ..Default::default()
-            },
+        let (token_modifier, token_type) = match inline {
+            Inline::Bold(_) => (TokenModifier::Bold, TokenType::Paragraph),
+            Inline::Italic(_) => (TokenModifier::Italic, TokenType::Paragraph),
+            Inline::Underline(_) => (TokenModifier::Underline, TokenType::Paragraph),
+            Inline::Subscript(_) => (TokenModifier::Subscript, TokenType::Paragraph),
+            Inline::Superscript(_) => (TokenModifier::Superscript, TokenType::Paragraph),
+            Inline::Overline(_) => (TokenModifier::Overline, TokenType::Paragraph),
+            Inline::Strikethrough(_) => (TokenModifier::Strikethrough, TokenType::Paragraph),
+            Inline::Highlight(_) => (TokenModifier::Highlight, TokenType::Paragraph),
+            Inline::Quote(_) => (TokenModifier::Plain, TokenType::Quote),
+            Inline::Attributes(_) => (TokenModifier::Plain, TokenType::Attribute),
+            Inline::Substitution(_) => (TokenModifier::Plain, TokenType::Substitution),
+            _ => (TokenModifier::Plain, TokenType::Paragraph),
+        };
+
+        OpenTokenModifier {
+            token_modifier,
+            token_type,
+            ..Default::default()
         }
     }
 }
 
-fn get_modifier_bitfield(modifiers: &Vec<OpenTokenModifier>) -> u32 {
+/// Surrounds the given content tokens with tokens for the delimiters of the inline.
+///
+/// Delimiters get the [`TokenType::Delimiter`] type, but keep the modifiers of all open formats.
+/// Their length is counted in characters, because delimiters like `‾` are longer in bytes.
+fn enclosed_tokens(
+    inline: &Inline,
+    content_tokens: Vec<SemanticToken>,
+    open_modifiers: &[OpenTokenModifier],
+) -> Vec<SemanticToken> {
+    let delimiters: TokenDelimiters = inline.delimiters();
+    let modifier_bitfield = get_modifier_bitfield(open_modifiers);
+
+    let mut tokens = vec![SemanticToken {
+        delta_line: inline.span().start().line as u32,
+        delta_start: inline.span().start().column as u32,
+        length: delimiters.open().as_str().chars().count() as u32,
+        token_type: TokenType::Delimiter.value(),
+        token_modifiers_bitset: modifier_bitfield,
+    }];
+
+    tokens.extend(content_tokens);
+
+    if let Some(closing_delim) = delimiters.close() {
+        let closing_delim_len = closing_delim.as_str().chars().count();
+
+        tokens.push(SemanticToken {
+            delta_line: inline.span().end().line as u32,
+            delta_start: (inline.span().end().column + 1 - closing_delim_len) as u32,
+            length: closing_delim_len as u32,
+            token_type: TokenType::Delimiter.value(),
+            token_modifiers_bitset: modifier_bitfield,
+        });
+    }
+
+    tokens
+}
+
+/// Returns the token type of the innermost open inline that changes the type of its content.
+fn get_content_type(open_modifiers: &[OpenTokenModifier]) -> TokenType {
+    open_modifiers
+        .iter()
+        .rev()
+        .map(|open_modifier| open_modifier.token_type)
+        .find(|token_type| *token_type != TokenType::Paragraph)
+        .unwrap_or_default()
+}
+
+fn get_modifier_bitfield(modifiers: &[OpenTokenModifier]) -> u32 {
     let mut field = 0;
 
     for modifier in modifiers {
@@ -0,0 +1,215 @@
+use lsp_types::{
+    ClientCapabilities, SemanticTokenModifier, SemanticTokenType, SemanticTokensLegend,
+};
+
+use super::TokenValue;
+
+/// Semantic token types of Unimarkup elements.
+///
+/// The legend index of a type is its position in [`TokenType::ALL`].
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenType {
+    #[default]
+    Paragraph,
+    Heading,
+    Delimiter,
+    Verbatim,
+    Math,
+    /// Target of a link, e.g. `target` in `[text](target)`.
+    Link,
+    Attribute,
+    Substitution,
+    Quote,
+    /// Language identifier of a verbatim block.
+    Language,
+    Comment,
+    // Types of code highlighted inside verbatim blocks
+    Keyword,
+    String,
+    Number,
+    Function,
+    Type,
+    Parameter,
+    Variable,
+    Operator,
+}
+
+impl TokenType {
+    /// All token types in the order they are registered in the legend.
+    pub const ALL: [TokenType; 19] = [
+        TokenType::Paragraph,
+        TokenType::Heading,
+        TokenType::Delimiter,
+        TokenType::Verbatim,
+        TokenType::Math,
+        TokenType::Link,
+        TokenType::Attribute,
+        TokenType::Substitution,
+        TokenType::Quote,
+        TokenType::Language,
+        TokenType::Comment,
+        TokenType::Keyword,
+        TokenType::String,
+        TokenType::Number,
+        TokenType::Function,
+        TokenType::Type,
+        TokenType::Parameter,
+        TokenType::Variable,
+        TokenType::Operator,
+    ];
+
+    /// Name of the token type that is registered in the legend.
+    ///
+    /// Standard LSP types are only used if their meaning matches the Unimarkup element.
+    pub fn name(&self) -> SemanticTokenType {
+        match self {
+            TokenType::Paragraph => SemanticTokenType::new("paragraph"),
+            TokenType::Heading => SemanticTokenType::new("heading"),
+            TokenType::Delimiter => SemanticTokenType::new("delimiter"),
+            TokenType::Verbatim => SemanticTokenType::new("verbatim"),
+            TokenType::Math => SemanticTokenType::new("math"),
+            TokenType::Link => SemanticTokenType::new("link"),
+            TokenType::Attribute => SemanticTokenType::new("attribute"),
+            TokenType::Substitution => SemanticTokenType::new("substitution"),
+            TokenType::Quote => SemanticTokenType::new("quote"),
+            TokenType::Language => SemanticTokenType::new("language"),
+            TokenType::Comment => SemanticTokenType::COMMENT,
+            TokenType::Keyword => SemanticTokenType::KEYWORD,
+            TokenType::String => SemanticTokenType::STRING,
+            TokenType::Number => SemanticTokenType::NUMBER,
+            TokenType::Function => SemanticTokenType::FUNCTION,
+            TokenType::Type => SemanticTokenType::TYPE,
+            TokenType::Parameter => SemanticTokenType::PARAMETER,
+            TokenType::Variable => SemanticTokenType::VARIABLE,
+            TokenType::Operator => SemanticTokenType::OPERATOR,
+        }
+    }
+
+    /// Standard LSP type that is registered instead of a Unimarkup type the client does not know.
+    ///
+    /// Heading level markers are highlighted like keywords, and delimiters like operators,
+    /// because both are syntax around the text.
+    /// Prose types like paragraphs and quotes have no fallback, so they are never colored like code.
+    pub fn fallback(&self) -> Option<SemanticTokenType> {
+        match self {
+            TokenType::Heading => Some(SemanticTokenType::KEYWORD),
+            TokenType::Delimiter => Some(SemanticTokenType::OPERATOR),
+            TokenType::Verbatim => Some(SemanticTokenType::STRING),
+            TokenType::Attribute => Some(SemanticTokenType::DECORATOR),
+            TokenType::Substitution => Some(SemanticTokenType::MACRO),
+            _ => None,
+        }
+    }
+}
+
+impl TokenValue for TokenType {
+    fn value(&self) -> u32 {
+        TokenType::ALL
+            .iter()
+            .position(|token_type| token_type == self)
+            .expect("Every token type must be part of the legend.") as u32
+    }
+}
+
+/// Semantic token modifiers for Unimarkup text formats.
+///
+/// The legend bit of a modifier is its position in [`TokenModifier::ALL`].
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenModifier {
+    /// Text without format. Sets no modifier bit.
+    #[default]
+    Plain,
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+    Subscript,
+    Superscript,
+    Overline,
+    Highlight,
+}
+
+impl TokenModifier {
+    /// All token modifiers in the order they are registered in the legend.
+    pub const ALL: [TokenModifier; 8] = [
+        TokenModifier::Bold,
+        TokenModifier::Italic,
+        TokenModifier::Underline,
+        TokenModifier::Strikethrough,
+        TokenModifier::Subscript,
+        TokenModifier::Superscript,
+        TokenModifier::Overline,
+        TokenModifier::Highlight,
+    ];
+
+    /// Name of the token modifier that is registered in the legend.
+    pub fn name(&self) -> SemanticTokenModifier {
+        match self {
+            TokenModifier::Plain => SemanticTokenModifier::new("plain"),
+            TokenModifier::Bold => SemanticTokenModifier::new("bold"),
+            TokenModifier::Italic => SemanticTokenModifier::new("italic"),
+            TokenModifier::Underline => SemanticTokenModifier::new("underline"),
+            TokenModifier::Strikethrough => SemanticTokenModifier::new("strikethrough"),
+            TokenModifier::Subscript => SemanticTokenModifier::new("subscript"),
+            TokenModifier::Superscript => SemanticTokenModifier::new("superscript"),
+            TokenModifier::Overline => SemanticTokenModifier::new("overline"),
+            TokenModifier::Highlight => SemanticTokenModifier::new("highlight"),
+        }
+    }
+}
+
+impl TokenValue for TokenModifier {
+    fn value(&self) -> u32 {
+        // Note: These values must set the correct modifier bit
+        match TokenModifier::ALL
+            .iter()
+            .position(|token_modifier| token_modifier == self)
+        {
+            Some(index) => 1 << index,
+            None => 0,
+        }
+    }
+}
+
+/// Creates the semantic token legend registered for the given client.
+///
+/// Unimarkup types are replaced by their [`TokenType::fallback`],
+/// if the client lists the token types it supports, but not the Unimarkup type.
+/// Token indices are the same in every legend.
+pub fn negotiate_semantic_token_legend(capabilities: &ClientCapabilities) -> SemanticTokensLegend {
+    let supported_types = capabilities
+        .text_document
+        .as_ref()
+        .and_then(|text_document| text_document.semantic_tokens.as_ref())
+        .map(|semantic_tokens| semantic_tokens.token_types.as_slice())
+        .unwrap_or_default();
+
+    let mut legend = create_semantic_token_legend();
+    if supported_types.is_empty() {
+        return legend;
+    }
+
+    for (token_type, name) in TokenType::ALL.iter().zip(legend.token_types.iter_mut()) {
+        if let Some(fallback) = token_type.fallback() {
+            if !supported_types.contains(name) {
+                *name = fallback;
+            }
+        }
+    }
+
+    legend
+}
+
+/// Creates the semantic token legend from [`TokenType::ALL`] and [`TokenModifier::ALL`].
+pub fn create_semantic_token_legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TokenType::ALL
+            .iter()
+            .map(|token_type| token_type.name())
+            .collect(),
+        token_modifiers: TokenModifier::ALL
+            .iter()
+            .map(|token_modifier| token_modifier.name())
+            .collect(),
+    }
+}
@@ -1,32 +1,97 @@
+use std::collections::HashMap;
+
 use lsp_server::RequestId;
 use lsp_server::Response;
+use lsp_types::ClientCapabilities;
+use lsp_types::Range;
 use lsp_types::SemanticToken;
 use lsp_types::SemanticTokens;
+use lsp_types::SemanticTokensDelta;
+use lsp_types::SemanticTokensDeltaParams;
+use lsp_types::SemanticTokensEdit;
+use lsp_types::SemanticTokensFullDeltaResult;
 use lsp_types::SemanticTokensParams;
+use lsp_types::SemanticTokensRangeParams;
+use lsp_types::SemanticTokensRangeResult;
 use lsp_types::SemanticTokensResult;
+use lsp_types::Url;
 use unimarkup_core::document::Document;
 
 use self::block_tokens::SemanticBlockTokenizer;
+use crate::blocks::block_lines;
+use crate::doc_sync::VersionedDocument;
+use crate::line_index::LineIndex;
 
 mod block_tokens;
+mod embedded_tokens;
 mod inline_tokens;
+pub mod legend;
 
 trait TokenValue {
     fn value(&self) -> u32;
 }
 
-pub fn get_semantic_tokens_response(
+/// Number of `u32` values a [`SemanticToken`] is encoded with.
+const TOKEN_ENCODING_LEN: u32 = 5;
+
+/// Semantic tokens that were last sent for each document.
+///
+/// Needed to answer `textDocument/semanticTokens/full/delta` requests.
+#[derive(Debug, Default)]
+pub(crate) struct SemanticTokensCache {
+    /// Used to create unique result ids.
+    result_cnt: u64,
+    docs: HashMap<Url, DocTokens>,
+}
+
+#[derive(Debug)]
+struct DocTokens {
+    result_id: String,
+    data: Vec<SemanticToken>,
+}
+
+impl SemanticTokensCache {
+    /// Stores the tokens of a document and returns the result id they are sent with.
+    fn store(&mut self, uri: Url, data: Vec<SemanticToken>) -> String {
+        self.result_cnt += 1;
+        let result_id = self.result_cnt.to_string();
+
+        self.docs.insert(
+            uri,
+            DocTokens {
+                result_id: result_id.clone(),
+                data,
+            },
+        );
+
+        result_id
+    }
+
+    /// Returns the cached tokens of a document if they were sent with the given result id.
+    fn get(&self, uri: &Url, result_id: &str) -> Option<&[SemanticToken]> {
+        self.docs
+            .get(uri)
+            .filter(|doc_tokens| doc_tokens.result_id == result_id)
+            .map(|doc_tokens| doc_tokens.data.as_slice())
+    }
+
+    pub(crate) fn remove(&mut self, uri: &Url) {
+        self.docs.remove(uri);
+    }
+}
+
+pub(crate) fn get_semantic_tokens_response(
     id: RequestId,
-    _params: SemanticTokensParams,
-    document: Option<&Document>,
+    params: SemanticTokensParams,
+    document: Option<&VersionedDocument>,
+    support: SemanticTokensSupport,
+    cache: &mut SemanticTokensCache,
 ) -> Response {
-    let mut tokens = SemanticTokens {
-        result_id: Some(id.to_string()),
-        ..Default::default()
-    };
+    let mut tokens = SemanticTokens::default();
 
     if let Some(um_doc) = document {
-        tokens.data = get_semantic_tokens(um_doc);
+        tokens.data = get_semantic_tokens(&um_doc.doc, &um_doc.line_index, support);
+        tokens.result_id = Some(cache.store(params.text_document.uri, tokens.data.clone()));
     }
 
     let result = Some(SemanticTokensResult::Tokens(tokens));
@@ -38,8 +103,285 @@ pub fn get_semantic_tokens_response(
     }
 }
 
-pub fn get_semantic_tokens(document: &Document) -> Vec<SemanticToken> {
-    make_relative(document.tokens())
+/// Answers a `textDocument/semanticTokens/full/delta` request.
+///
+/// Edits are only sent if the tokens of the previous result id are still cached.
+/// Otherwise, all tokens of the document are sent.
+pub(crate) fn get_semantic_tokens_delta_response(
+    id: RequestId,
+    params: SemanticTokensDeltaParams,
+    document: Option<&VersionedDocument>,
+    support: SemanticTokensSupport,
+    cache: &mut SemanticTokensCache,
+) -> Response {
+    let uri = params.text_document.uri;
+
+    let result = match document {
+        Some(um_doc) => {
+            let data = get_semantic_tokens(&um_doc.doc, &um_doc.line_index, support);
+            let edits = cache
+                .get(&uri, &params.previous_result_id)
+                .map(|previous| get_semantic_tokens_edits(previous, &data));
+            let result_id = Some(cache.store(uri, data.clone()));
+
+            match edits {
+                Some(edits) => SemanticTokensFullDeltaResult::TokensDelta(SemanticTokensDelta {
+                    result_id,
+                    edits,
+                }),
+                None => SemanticTokensFullDeltaResult::Tokens(SemanticTokens { result_id, data }),
+            }
+        }
+        None => SemanticTokensFullDeltaResult::Tokens(SemanticTokens::default()),
+    };
+
+    let result = Some(result);
+    let result = serde_json::to_value(&result).unwrap();
+    Response {
+        id,
+        result: Some(result),
+        error: None,
+    }
+}
+
+/// Answers a `textDocument/semanticTokens/range` request.
+pub(crate) fn get_semantic_tokens_range_response(
+    id: RequestId,
+    params: SemanticTokensRangeParams,
+    document: Option<&VersionedDocument>,
+    support: SemanticTokensSupport,
+) -> Response {
+    let mut tokens = SemanticTokens::default();
+
+    if let Some(um_doc) = document {
+        tokens.data =
+            get_semantic_tokens_in_range(&um_doc.doc, &um_doc.line_index, params.range, support);
+    }
+
+    let result = Some(SemanticTokensRangeResult::Tokens(tokens));
+    let result = serde_json::to_value(&result).unwrap();
+    Response {
+        id,
+        result: Some(result),
+        error: None,
+    }
+}
+
+/// Creates the edits that turn the previous tokens into the current tokens.
+///
+/// Tokens at the start and end that did not change are skipped,
+/// so at most one edit replacing the changed tokens in between is created.
+pub fn get_semantic_tokens_edits(
+    previous: &[SemanticToken],
+    current: &[SemanticToken],
+) -> Vec<SemanticTokensEdit> {
+    let prefix_len = previous
+        .iter()
+        .zip(current)
+        .take_while(|(prev_token, token)| prev_token == token)
+        .count();
+
+    let max_suffix_len = previous.len().min(current.len()) - prefix_len;
+    let suffix_len = previous
+        .iter()
+        .rev()
+        .zip(current.iter().rev())
+        .take(max_suffix_len)
+        .take_while(|(prev_token, token)| prev_token == token)
+        .count();
+
+    let deleted_len = previous.len() - prefix_len - suffix_len;
+    let inserted = &current[prefix_len..current.len() - suffix_len];
+
+    if deleted_len == 0 && inserted.is_empty() {
+        return vec![];
+    }
+
+    vec![SemanticTokensEdit {
+        start: prefix_len as u32 * TOKEN_ENCODING_LEN,
+        delete_count: deleted_len as u32 * TOKEN_ENCODING_LEN,
+        data: (!inserted.is_empty()).then(|| inserted.to_vec()),
+    }]
+}
+
+/// Semantic token features that are optional for clients.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SemanticTokensSupport {
+    /// Tokens may span multiple lines.
+    pub multiline: bool,
+    /// Tokens may overlap each other.
+    pub overlapping: bool,
+}
+
+impl SemanticTokensSupport {
+    pub fn from_capabilities(capabilities: &ClientCapabilities) -> Self {
+        let semantic_tokens = capabilities
+            .text_document
+            .as_ref()
+            .and_then(|text_document| text_document.semantic_tokens.as_ref());
+
+        SemanticTokensSupport {
+            multiline: semantic_tokens
+                .and_then(|semantic_tokens| semantic_tokens.multiline_token_support)
+                == Some(true),
+            overlapping: semantic_tokens
+                .and_then(|semantic_tokens| semantic_tokens.overlapping_token_support)
+                == Some(true),
+        }
+    }
+}
+
+pub fn get_semantic_tokens(
+    document: &Document,
+    line_index: &LineIndex,
+    support: SemanticTokensSupport,
+) -> Vec<SemanticToken> {
+    finish_tokens(document.tokens(line_index), line_index, support)
+}
+
+/// Returns the tokens of all blocks that intersect the given range.
+///
+/// Only these blocks are tokenized, but tokens are still relative to the document start.
+pub fn get_semantic_tokens_in_range(
+    document: &Document,
+    line_index: &LineIndex,
+    range: Range,
+    support: SemanticTokensSupport,
+) -> Vec<SemanticToken> {
+    // Unimarkup lines start at 1
+    let start_line = range.start.line as usize + 1;
+    let end_line = range.end.line as usize + 1;
+
+    let tokens = document
+        .blocks
+        .iter()
+        .filter(|block| {
+            block_lines(block)
+                .is_some_and(|lines| *lines.start() <= end_line && *lines.end() >= start_line)
+        })
+        .flat_map(|block| block.tokens(line_index))
+        .collect();
+
+    finish_tokens(tokens, line_index, support)
+}
+
+/// Turns the tokens of Unimarkup elements into a token stream that is valid for the client.
+///
+/// Tokens must still have absolute positions starting at line = 1 and column = 1.
+fn finish_tokens(
+    tokens: Vec<SemanticToken>,
+    line_index: &LineIndex,
+    support: SemanticTokensSupport,
+) -> Vec<SemanticToken> {
+    let tokens = if support.multiline {
+        tokens
+    } else {
+        split_multiline(tokens, line_index)
+    };
+
+    let tokens = encode_positions(tokens, line_index);
+
+    let tokens = if support.overlapping {
+        tokens
+    } else {
+        remove_overlaps(tokens)
+    };
+
+    make_relative(tokens)
+}
+
+/// Splits tokens that span multiple lines into one token per line.
+///
+/// Line breaks are part of the character length of a token, but not of the split tokens.
+/// Their length is taken from the text, because lines may end with `\n` or `\r\n`.
+fn split_multiline(tokens: Vec<SemanticToken>, line_index: &LineIndex) -> Vec<SemanticToken> {
+    let mut split_tokens = Vec::with_capacity(tokens.len());
+
+    for token in tokens {
+        let mut line = token.delta_line;
+        let mut column = token.delta_start;
+        let mut remaining = token.length;
+
+        while remaining > 0 {
+            let line_len = line_index.line(line.saturating_sub(1)).chars().count() as u32;
+            let available = (line_len + 1).saturating_sub(column);
+
+            if remaining <= available || line >= line_index.line_count() {
+                split_tokens.push(SemanticToken {
+                    delta_line: line,
+                    delta_start: column,
+                    length: remaining,
+                    ..token
+                });
+                break;
+            }
+
+            if available > 0 {
+                split_tokens.push(SemanticToken {
+                    delta_line: line,
+                    delta_start: column,
+                    length: available,
+                    ..token
+                });
+            }
+
+            let line_break_len = line_index.line_break_len(line.saturating_sub(1));
+            remaining = remaining.saturating_sub(available + line_break_len);
+            line += 1;
+            column = 1;
+        }
+    }
+
+    split_tokens
+}
+
+/// Trims tokens that overlap a preceding token, and removes tokens that are fully covered.
+///
+/// Tokens must be on one line.
+fn remove_overlaps(mut tokens: Vec<SemanticToken>) -> Vec<SemanticToken> {
+    tokens.sort_by_key(|token| (token.delta_line, token.delta_start));
+
+    let mut visible_tokens: Vec<SemanticToken> = Vec::with_capacity(tokens.len());
+
+    for mut token in tokens {
+        if let Some(previous) = visible_tokens.last() {
+            let previous_end = previous.delta_start + previous.length;
+
+            if previous.delta_line == token.delta_line && token.delta_start < previous_end {
+                let token_end = token.delta_start + token.length;
+                if token_end <= previous_end {
+                    continue;
+                }
+
+                token.length = token_end - previous_end;
+                token.delta_start = previous_end;
+            }
+        }
+
+        if token.length > 0 {
+            visible_tokens.push(token);
+        }
+    }
+
+    visible_tokens
+}
+
+/// Converts the character columns and lengths of all tokens
+/// into code units of the position encoding of the line index.
+///
+/// Lengths of multiline tokens are encoded line by line.
+///
+/// Tokens must still have absolute positions starting at line = 1 and column = 1.
+fn encode_positions(mut tokens: Vec<SemanticToken>, line_index: &LineIndex) -> Vec<SemanticToken> {
+    for token in tokens.iter_mut() {
+        let line = token.delta_line.saturating_sub(1);
+        let char_column = token.delta_start.saturating_sub(1);
+
+        token.length = line_index.encoded_len(line, char_column, token.length);
+        token.delta_start = line_index.encoded_column(line, char_column) + 1;
+    }
+
+    tokens
 }
 
 /// Brings all tokens in relative position offsets
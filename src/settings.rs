@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+use unimarkup_core::config::{Config, OutputFormat};
+
+/// Name of the configuration section the client stores the server settings in.
+pub const SETTINGS_SECTION: &str = "unimarkup";
+
+/// Time to wait after the last change of a document before it gets compiled.
+pub const DEFAULT_COMPILE_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Settings of the language server set by the client.
+///
+/// Settings are received with `initializationOptions`, `workspace/configuration`
+/// and `workspace/didChangeConfiguration`.
+/// Unset settings keep the default value of the Unimarkup [`Config`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    /// Time in milliseconds to wait after the last change of a document before it gets compiled.
+    pub compile_debounce_ms: Option<u64>,
+    pub insert_paths: Option<Vec<PathBuf>>,
+    pub dot_path: Option<PathBuf>,
+    pub theme: Option<PathBuf>,
+    pub flags: Option<Vec<String>>,
+    pub citation_style: Option<PathBuf>,
+    pub references: Option<Vec<PathBuf>>,
+    pub fonts: Option<Vec<PathBuf>>,
+    pub html_template: Option<PathBuf>,
+    pub html_embed_svg: Option<bool>,
+}
+
+impl Settings {
+    /// Reads settings from a JSON value sent by the client.
+    ///
+    /// The value may either contain the settings directly,
+    /// or nest them in the [`SETTINGS_SECTION`].
+    /// Returns `None` if the value contains no valid settings.
+    pub fn from_value(value: &serde_json::Value) -> Option<Settings> {
+        let value = value.get(SETTINGS_SECTION).unwrap_or(value);
+
+        if !value.is_object() {
+            return None;
+        }
+
+        serde_json::from_value(value.clone()).ok()
+    }
+
+    pub fn compile_debounce(&self) -> Duration {
+        self.compile_debounce_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_COMPILE_DEBOUNCE)
+    }
+
+    /// Creates the Unimarkup [`Config`] used to compile documents.
+    ///
+    /// HTML output is always enabled, because it is needed for the preview.
+    pub fn to_config(&self) -> Config {
+        let mut config = Config {
+            out_formats: Some(vec![OutputFormat::Html]),
+            ..Default::default()
+        };
+
+        self.apply_to(&mut config);
+        config
+    }
+
+    /// Overwrites all fields of the given config that are set in these settings.
+    pub fn apply_to(&self, config: &mut Config) {
+        if let Some(insert_paths) = &self.insert_paths {
+            config.insert_paths = Some(insert_paths.clone());
+        }
+        if let Some(dot_path) = &self.dot_path {
+            config.dot_path = Some(dot_path.clone());
+        }
+        if let Some(theme) = &self.theme {
+            config.theme = Some(theme.clone());
+        }
+        if let Some(flags) = &self.flags {
+            config.flags = Some(flags.clone());
+        }
+        if let Some(citation_style) = &self.citation_style {
+            config.citation_style = Some(citation_style.clone());
+        }
+        if let Some(references) = &self.references {
+            config.references = Some(references.clone());
+        }
+        if let Some(fonts) = &self.fonts {
+            config.fonts = Some(fonts.clone());
+        }
+        if let Some(html_template) = &self.html_template {
+            config.html_template = Some(html_template.clone());
+        }
+        if let Some(html_embed_svg) = self.html_embed_svg {
+            config.html_embed_svg = html_embed_svg;
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::path::{Path, PathBuf};
+
+/// File extension of Unimarkup files.
+pub const UM_FILE_EXTENSION: &str = "um";
+
+/// Directories that never contain Unimarkup files of the workspace.
+const SKIPPED_DIRS: [&str; 2] = ["target", "node_modules"];
+
+/// Returns `true` if the given path is a Unimarkup file.
+pub fn is_um_file(path: &Path) -> bool {
+    path.extension() == Some(UM_FILE_EXTENSION.as_ref())
+}
+
+/// Recursively collects all Unimarkup files inside the given directory.
+///
+/// Hidden directories and build output directories are skipped.
+pub fn find_um_files(dir: &Path) -> Vec<PathBuf> {
+    let mut um_files = Vec::new();
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return um_files;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        if file_type.is_dir() {
+            let skipped = path.file_name().map_or(true, |dir_name| {
+                let dir_name = dir_name.to_string_lossy();
+                dir_name.starts_with('.') || SKIPPED_DIRS.contains(&dir_name.as_ref())
+            });
+
+            if !skipped {
+                um_files.append(&mut find_um_files(&path));
+            }
+        } else if file_type.is_file() && is_um_file(&path) {
+            um_files.push(path);
+        }
+    }
+
+    um_files
+}
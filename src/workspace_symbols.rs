@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+use lsp_server::{RequestId, Response};
+use lsp_types::{
+    ClientCapabilities, Location, OneOf, SymbolInformation, SymbolKind, Url, WorkspaceLocation,
+    WorkspaceSymbol, WorkspaceSymbolParams, WorkspaceSymbolResponse,
+};
+use unimarkup_core::document::Document;
+
+use crate::blocks::{elements, inlines_text};
+use crate::doc_sync::VersionedDocument;
+use crate::line_index::LineIndex;
+
+/// Maximum number of symbols returned for one query.
+pub const MAX_WORKSPACE_SYMBOLS: usize = 128;
+
+/// Answers a `workspace/symbol` request with the best matching headings and ids
+/// of all documents in the document store.
+///
+/// Clients that resolve locations lazily get symbols without ranges.
+/// All other clients get symbols with their full location.
+pub(crate) fn get_workspace_symbols_response(
+    id: RequestId,
+    params: WorkspaceSymbolParams,
+    documents: &HashMap<Url, VersionedDocument>,
+    resolve_support: bool,
+) -> Response {
+    let symbols = find_workspace_symbols(
+        &params.query,
+        documents.iter().map(|(uri, um_doc)| (uri, &um_doc.doc)),
+    );
+
+    let result = if resolve_support {
+        WorkspaceSymbolResponse::Nested(symbols)
+    } else {
+        WorkspaceSymbolResponse::Flat(
+            symbols
+                .into_iter()
+                .filter_map(|symbol| {
+                    let document = documents.get(symbol_uri(&symbol))?;
+                    symbol_information(resolve_workspace_symbol(
+                        symbol,
+                        &document.doc,
+                        &document.line_index,
+                    ))
+                })
+                .collect(),
+        )
+    };
+
+    let result = serde_json::to_value(&result).unwrap();
+    Response {
+        id,
+        result: Some(result),
+        error: None,
+    }
+}
+
+/// Answers a `workspaceSymbol/resolve` request by adding the range to the symbol location.
+pub(crate) fn get_workspace_symbol_resolve_response(
+    id: RequestId,
+    symbol: WorkspaceSymbol,
+    documents: &HashMap<Url, VersionedDocument>,
+) -> Response {
+    let symbol = match documents.get(symbol_uri(&symbol)) {
+        Some(document) => resolve_workspace_symbol(symbol, &document.doc, &document.line_index),
+        None => symbol,
+    };
+
+    let result = serde_json::to_value(&symbol).unwrap();
+    Response {
+        id,
+        result: Some(result),
+        error: None,
+    }
+}
+
+/// Returns `true` if the client can resolve the range of workspace symbol locations lazily.
+pub fn workspace_symbol_resolve_supported(capabilities: &ClientCapabilities) -> bool {
+    capabilities
+        .workspace
+        .as_ref()
+        .and_then(|workspace| workspace.symbol.as_ref())
+        .and_then(|symbol| symbol.resolve_support.as_ref())
+        .is_some_and(|resolve_support| {
+            resolve_support
+                .properties
+                .iter()
+                .any(|property| property == "location.range")
+        })
+}
+
+/// Finds the headings and element ids matching the query in all given documents.
+///
+/// Headings match by their text or id, all other elements by their id.
+/// Symbols are ranked by their [`fuzzy_score`], and only contain the document
+/// of their location. The range is added by [`resolve_workspace_symbol`].
+pub fn find_workspace_symbols<'a>(
+    query: &str,
+    documents: impl IntoIterator<Item = (&'a Url, &'a Document)>,
+) -> Vec<WorkspaceSymbol> {
+    let mut matches = Vec::new();
+
+    for (uri, document) in documents {
+        for element in elements(&document.blocks) {
+            let (name, kind) = match element.heading {
+                Some(heading) => (inlines_text(&heading.content), SymbolKind::STRING),
+                None => (element.id.to_string(), SymbolKind::KEY),
+            };
+
+            let score = fuzzy_score(query, &name)
+                .into_iter()
+                .chain(fuzzy_score(query, element.id))
+                .max();
+            let Some(score) = score else {
+                continue;
+            };
+
+            let name = if name.is_empty() {
+                element.id.to_string()
+            } else {
+                name
+            };
+
+            matches.push((
+                score,
+                WorkspaceSymbol {
+                    name,
+                    kind,
+                    tags: None,
+                    container_name: file_name(uri),
+                    location: OneOf::Right(WorkspaceLocation { uri: uri.clone() }),
+                    data: Some(serde_json::Value::String(element.id.to_string())),
+                },
+            ));
+        }
+    }
+
+    // Best matches first, shorter names are the closer matches on equal scores
+    matches.sort_by(|(score, symbol), (other_score, other_symbol)| {
+        other_score
+            .cmp(score)
+            .then_with(|| symbol.name.len().cmp(&other_symbol.name.len()))
+            .then_with(|| symbol.name.cmp(&other_symbol.name))
+    });
+
+    matches
+        .into_iter()
+        .take(MAX_WORKSPACE_SYMBOLS)
+        .map(|(_, symbol)| symbol)
+        .collect()
+}
+
+/// Adds the range of the element the symbol refers to to the symbol location.
+///
+/// Heading symbols cover the heading line, all other symbols cover their element.
+/// Symbols whose element no longer exists are returned unchanged.
+pub fn resolve_workspace_symbol(
+    mut symbol: WorkspaceSymbol,
+    document: &Document,
+    line_index: &LineIndex,
+) -> WorkspaceSymbol {
+    let Some(element_id) = symbol.data.as_ref().and_then(|data| data.as_str()) else {
+        return symbol;
+    };
+
+    let element = elements(&document.blocks)
+        .into_iter()
+        .find(|element| element.id == element_id);
+
+    if let Some(element) = element {
+        let uri = symbol_uri(&symbol).clone();
+        symbol.location = OneOf::Left(Location::new(uri, element.range(line_index)));
+    }
+
+    symbol
+}
+
+/// Scores how well the query matches the candidate, ignoring case and whitespace of the query.
+///
+/// Returns `None` if the query characters are not found in order in the candidate.
+/// Matches at the start of the candidate or of a word, and consecutive matches score higher.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<usize> {
+    let mut query_chars = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .peekable();
+
+    let mut score = 0;
+    let mut previous_char: Option<char> = None;
+    let mut previous_matched = false;
+
+    for c in candidate.chars() {
+        let Some(&query_char) = query_chars.peek() else {
+            break;
+        };
+
+        let matched = c.to_lowercase().eq(std::iter::once(query_char));
+        if matched {
+            query_chars.next();
+            score += 1;
+
+            match previous_char {
+                None => score += 8,
+                Some(previous) if !previous.is_alphanumeric() => score += 6,
+                Some(previous) if previous.is_lowercase() && c.is_uppercase() => score += 6,
+                _ => {}
+            }
+
+            if previous_matched {
+                score += 4;
+            }
+        }
+
+        previous_matched = matched;
+        previous_char = Some(c);
+    }
+
+    if query_chars.peek().is_some() {
+        return None;
+    }
+
+    Some(score)
+}
+
+fn symbol_uri(symbol: &WorkspaceSymbol) -> &Url {
+    match &symbol.location {
+        OneOf::Left(location) => &location.uri,
+        OneOf::Right(workspace_location) => &workspace_location.uri,
+    }
+}
+
+fn file_name(uri: &Url) -> Option<String> {
+    uri.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .map(str::to_string)
+}
+
+/// Converts a resolved symbol for clients that do not support workspace symbols.
+#[allow(deprecated)]
+fn symbol_information(symbol: WorkspaceSymbol) -> Option<SymbolInformation> {
+    let OneOf::Left(location) = symbol.location else {
+        return None;
+    };
+
+    Some(SymbolInformation {
+        name: symbol.name,
+        kind: symbol.kind,
+        tags: symbol.tags,
+        deprecated: None,
+        location,
+        container_name: symbol.container_name,
+    })
+}
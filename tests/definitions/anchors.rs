@@ -0,0 +1,115 @@
+use lsp_types::{Position, Range, Url};
+use unimarkup_core::config::Config;
+use unimarkup_core::document::Document;
+use unimarkup_lsp::definitions::{find_definitions, reference_at};
+use unimarkup_lsp::line_index::{LineIndex, PositionEncoding};
+
+struct TestDoc {
+    uri: Url,
+    document: Document,
+    line_index: LineIndex,
+}
+
+fn test_doc(uri: &str, input: &str) -> TestDoc {
+    TestDoc {
+        uri: Url::parse(uri).unwrap(),
+        document: unimarkup_core::unimarkup::compile(input, Config::default()).unwrap(),
+        line_index: LineIndex::new(input, PositionEncoding::Utf16),
+    }
+}
+
+fn documents(docs: &[TestDoc]) -> impl Iterator<Item = (&Url, &Document, &LineIndex)> {
+    docs.iter()
+        .map(|doc| (&doc.uri, &doc.document, &doc.line_index))
+}
+
+#[test]
+fn anchor_refers_to_heading_in_same_document() {
+    let docs = [test_doc(
+        "file:///docs/guide.um",
+        "See [usage](#usage)\n\n# Usage\n\ntext",
+    )];
+    let reference =
+        reference_at(&docs[0].document, &docs[0].line_index, Position::new(0, 14)).unwrap();
+
+    let definitions = find_definitions(&docs[0].uri, &reference, documents(&docs));
+
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(definitions[0].target_uri, docs[0].uri);
+    assert_eq!(
+        definitions[0].target_selection_range,
+        Range::new(Position::new(2, 0), Position::new(2, 7))
+    );
+}
+
+#[test]
+fn anchor_refers_to_heading_in_other_document() {
+    let docs = [
+        test_doc("file:///docs/readme.um", "See [usage](#usage)"),
+        test_doc("file:///docs/guide.um", "# Intro\n\n# Usage"),
+    ];
+    let reference =
+        reference_at(&docs[0].document, &docs[0].line_index, Position::new(0, 14)).unwrap();
+
+    let definitions = find_definitions(&docs[0].uri, &reference, documents(&docs));
+
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(definitions[0].target_uri, docs[1].uri);
+    assert_eq!(definitions[0].target_range.start, Position::new(2, 0));
+}
+
+#[test]
+fn path_limits_definitions_to_referenced_document() {
+    let docs = [
+        test_doc("file:///docs/readme.um", "See [usage](api#usage)"),
+        test_doc("file:///docs/guide.um", "# Usage"),
+        test_doc("file:///docs/api.um", "# Usage"),
+    ];
+    let reference =
+        reference_at(&docs[0].document, &docs[0].line_index, Position::new(0, 18)).unwrap();
+
+    let definitions = find_definitions(&docs[0].uri, &reference, documents(&docs));
+
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(definitions[0].target_uri, docs[2].uri);
+}
+
+#[test]
+fn definition_in_same_document_shadows_other_documents() {
+    let docs = [
+        test_doc("file:///docs/readme.um", "[usage](#usage)\n\n# Usage"),
+        test_doc("file:///docs/guide.um", "# Usage"),
+    ];
+    let reference =
+        reference_at(&docs[0].document, &docs[0].line_index, Position::new(0, 10)).unwrap();
+
+    let definitions = find_definitions(&docs[0].uri, &reference, documents(&docs));
+
+    assert_eq!(definitions.len(), 1);
+    assert_eq!(definitions[0].target_uri, docs[0].uri);
+}
+
+#[test]
+fn unknown_id_has_no_definition() {
+    let docs = [test_doc(
+        "file:///docs/readme.um",
+        "[usage](#missing)\n\n# Usage",
+    )];
+    let reference =
+        reference_at(&docs[0].document, &docs[0].line_index, Position::new(0, 10)).unwrap();
+
+    assert!(find_definitions(&docs[0].uri, &reference, documents(&docs)).is_empty());
+}
+
+#[test]
+fn plain_word_has_no_definition() {
+    let docs = [
+        test_doc("file:///docs/readme.um", "Read about usage here"),
+        test_doc("file:///docs/guide.um", "# Usage"),
+    ];
+
+    assert_eq!(
+        reference_at(&docs[0].document, &docs[0].line_index, Position::new(0, 13)),
+        None
+    );
+}
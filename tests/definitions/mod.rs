@@ -0,0 +1,2 @@
+mod anchors;
+mod references;
@@ -0,0 +1,73 @@
+use lsp_types::{Position, Range};
+use unimarkup_core::config::Config;
+use unimarkup_lsp::definitions::{reference_at, Reference};
+use unimarkup_lsp::line_index::{LineIndex, PositionEncoding};
+
+fn reference(input: &str, position: Position) -> Option<Reference> {
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    reference_at(&document, &line_index, position)
+}
+
+#[test]
+fn anchor_in_link_target_is_referenced() {
+    let reference = reference("See [usage](#usage-notes) below", Position::new(0, 16)).unwrap();
+
+    assert_eq!(reference.path, None);
+    assert_eq!(reference.id, "usage-notes");
+    assert_eq!(
+        reference.range,
+        Range::new(Position::new(0, 12), Position::new(0, 24))
+    );
+}
+
+#[test]
+fn anchor_in_other_document_keeps_path() {
+    let reference = reference("[guide](docs/guide.um#install)", Position::new(0, 10)).unwrap();
+
+    assert_eq!(reference.path.as_deref(), Some("docs/guide.um"));
+    assert_eq!(reference.id, "install");
+}
+
+#[test]
+fn id_in_attributes_is_referenced() {
+    let reference = reference("text {intro}", Position::new(0, 8)).unwrap();
+
+    assert_eq!(reference.path, None);
+    assert_eq!(reference.id, "intro");
+}
+
+#[test]
+fn anchor_in_heading_is_referenced() {
+    let reference = reference("##   See #usage", Position::new(0, 11)).unwrap();
+
+    assert_eq!(reference.id, "usage");
+    assert_eq!(
+        reference.range,
+        Range::new(Position::new(0, 9), Position::new(0, 15))
+    );
+}
+
+#[test]
+fn plain_word_is_no_reference() {
+    assert_eq!(
+        reference("# Usage\n\nSee the usage below", Position::new(2, 10)),
+        None
+    );
+}
+
+#[test]
+fn anchor_in_verbatim_is_no_reference() {
+    assert_eq!(reference("See `#usage` below", Position::new(0, 8)), None);
+}
+
+#[test]
+fn whitespace_is_no_reference() {
+    assert_eq!(reference("first  second", Position::new(0, 6)), None);
+}
+
+#[test]
+fn link_target_without_anchor_id_is_no_reference() {
+    assert_eq!(reference("[guide](guide.um#)", Position::new(0, 10)), None);
+}
@@ -0,0 +1,46 @@
+use lsp_types::{DiagnosticSeverity, Position, Range};
+use unimarkup_lsp::diagnostics::compile_error_diagnostics;
+use unimarkup_lsp::line_index::{LineIndex, PositionEncoding};
+
+#[test]
+fn error_spans_whole_document() {
+    let line_index = LineIndex::new("# heading\nsome paragraph", PositionEncoding::Utf16);
+    let diagnostics = compile_error_diagnostics(&"Invalid configuration", &line_index);
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].range,
+        Range::new(Position::new(0, 0), Position::new(1, 14))
+    );
+    assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+    assert_eq!(diagnostics[0].message, "Invalid configuration");
+}
+
+#[test]
+fn error_of_empty_document_starts_at_document_start() {
+    let diagnostics = compile_error_diagnostics(&"Invalid configuration", &LineIndex::default());
+
+    assert_eq!(diagnostics[0].range, Range::default());
+}
+
+#[test]
+fn lines_mentioned_in_message_are_ignored() {
+    let line_index = LineIndex::new("**inline**\nsecond line", PositionEncoding::Utf16);
+    let diagnostics = compile_error_diagnostics(
+        &"Unclosed inline at the underline of line 2, column 3",
+        &line_index,
+    );
+
+    assert_eq!(
+        diagnostics[0].range,
+        Range::new(Position::new(0, 0), Position::new(1, 11))
+    );
+}
+
+#[test]
+fn document_end_is_converted_into_position_encoding() {
+    let line_index = LineIndex::new("# heading\näöü heading", PositionEncoding::Utf8);
+    let diagnostics = compile_error_diagnostics(&"Invalid heading", &line_index);
+
+    assert_eq!(diagnostics[0].range.end, Position::new(1, 14));
+}
@@ -0,0 +1,2 @@
+mod compile_errors;
+mod reports;
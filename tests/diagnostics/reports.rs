@@ -0,0 +1,87 @@
+use lsp_types::{Diagnostic, DocumentDiagnosticReport, Position, Range, Url};
+use unimarkup_lsp::diagnostics::{get_diagnostic_report, DiagnosticsStore};
+
+fn doc_uri() -> Url {
+    Url::parse("file:///document.um").unwrap()
+}
+
+fn diagnostic(message: &str) -> Diagnostic {
+    Diagnostic {
+        range: Range::new(Position::new(0, 0), Position::new(0, 9)),
+        message: message.to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn report_with_matching_result_id_is_unchanged() {
+    let mut store = DiagnosticsStore::default();
+    store.update(doc_uri(), vec![diagnostic("Invalid heading")]);
+    let result_id = store.get(&doc_uri()).unwrap().result_id.clone();
+
+    let report = get_diagnostic_report(Some(&result_id), store.get(&doc_uri()));
+
+    match report {
+        DocumentDiagnosticReport::Unchanged(report) => {
+            assert_eq!(
+                report.unchanged_document_diagnostic_report.result_id,
+                result_id
+            );
+        }
+        DocumentDiagnosticReport::Full(_) => panic!("Expected an unchanged report."),
+    }
+}
+
+#[test]
+fn report_with_outdated_result_id_is_full() {
+    let mut store = DiagnosticsStore::default();
+    store.update(doc_uri(), vec![diagnostic("Invalid heading")]);
+    let outdated_id = store.get(&doc_uri()).unwrap().result_id.clone();
+    store.update(doc_uri(), vec![diagnostic("Invalid paragraph")]);
+
+    let report = get_diagnostic_report(Some(&outdated_id), store.get(&doc_uri()));
+
+    match report {
+        DocumentDiagnosticReport::Full(report) => {
+            let report = report.full_document_diagnostic_report;
+            assert_ne!(report.result_id, Some(outdated_id));
+            assert_eq!(report.items, vec![diagnostic("Invalid paragraph")]);
+        }
+        DocumentDiagnosticReport::Unchanged(_) => panic!("Expected a full report."),
+    }
+}
+
+#[test]
+fn first_report_is_full() {
+    let mut store = DiagnosticsStore::default();
+    store.update(doc_uri(), vec![diagnostic("Invalid heading")]);
+
+    let report = get_diagnostic_report(None, store.get(&doc_uri()));
+
+    assert!(matches!(report, DocumentDiagnosticReport::Full(_)));
+}
+
+#[test]
+fn unchanged_diagnostics_keep_result_id() {
+    let mut store = DiagnosticsStore::default();
+
+    assert!(store.update(doc_uri(), vec![diagnostic("Invalid heading")]));
+    let result_id = store.get(&doc_uri()).unwrap().result_id.clone();
+
+    assert!(!store.update(doc_uri(), vec![diagnostic("Invalid heading")]));
+    assert_eq!(store.get(&doc_uri()).unwrap().result_id, result_id);
+}
+
+#[test]
+fn closed_document_has_no_diagnostics() {
+    let mut store = DiagnosticsStore::default();
+    store.update(doc_uri(), vec![diagnostic("Invalid heading")]);
+
+    assert!(store.remove(&doc_uri()));
+
+    assert!(store.get(&doc_uri()).is_none());
+    let report = get_diagnostic_report(None, store.get(&doc_uri()));
+    assert_eq!(report, DocumentDiagnosticReport::Full(Default::default()));
+    // Closing the document again has nothing to clear
+    assert!(!store.remove(&doc_uri()));
+}
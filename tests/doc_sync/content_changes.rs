@@ -0,0 +1,128 @@
+use lsp_types::{Position, Range, TextDocumentContentChangeEvent};
+use unimarkup_lsp::doc_sync::apply_content_changes;
+use unimarkup_lsp::line_index::PositionEncoding;
+
+fn ranged_change(start: (u32, u32), end: (u32, u32), text: &str) -> TextDocumentContentChangeEvent {
+    TextDocumentContentChangeEvent {
+        range: Some(Range {
+            start: Position::new(start.0, start.1),
+            end: Position::new(end.0, end.1),
+        }),
+        range_length: None,
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn full_change_replaces_text() {
+    let mut text = String::from("# heading");
+
+    apply_content_changes(
+        &mut text,
+        &[TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "**bold**".to_string(),
+        }],
+        PositionEncoding::Utf16,
+    );
+
+    assert_eq!(text, "**bold**");
+}
+
+#[test]
+fn ranged_change_inserts_text() {
+    let mut text = String::from("# heading\n\nplain text");
+
+    apply_content_changes(
+        &mut text,
+        &[ranged_change((2, 6), (2, 6), "bold ")],
+        PositionEncoding::Utf16,
+    );
+
+    assert_eq!(text, "# heading\n\nplain bold text");
+}
+
+#[test]
+fn ranged_change_replaces_over_lines() {
+    let mut text = String::from("# heading\n\nplain text");
+
+    apply_content_changes(
+        &mut text,
+        &[ranged_change((0, 2), (2, 5), "other")],
+        PositionEncoding::Utf16,
+    );
+
+    assert_eq!(text, "# other text");
+}
+
+#[test]
+fn multiple_changes_are_applied_in_order() {
+    let mut text = String::from("plain text");
+
+    apply_content_changes(
+        &mut text,
+        &[
+            ranged_change((0, 0), (0, 0), "**"),
+            ranged_change((0, 7), (0, 7), "**"),
+            ranged_change((0, 14), (0, 14), "\nnew line"),
+        ],
+        PositionEncoding::Utf16,
+    );
+
+    assert_eq!(text, "**plain** text\nnew line");
+}
+
+#[test]
+fn ranged_change_uses_utf16_columns() {
+    let mut text = String::from("Grüße 🎉 text");
+
+    // The emoji takes two UTF-16 code units
+    apply_content_changes(
+        &mut text,
+        &[ranged_change((0, 9), (0, 13), "Text")],
+        PositionEncoding::Utf16,
+    );
+
+    assert_eq!(text, "Grüße 🎉 Text");
+}
+
+#[test]
+fn ranged_change_uses_utf8_columns() {
+    let mut text = String::from("Grüße 🎉 text");
+
+    // Umlauts take two bytes, the emoji takes four bytes
+    apply_content_changes(
+        &mut text,
+        &[ranged_change((0, 13), (0, 17), "Text")],
+        PositionEncoding::Utf8,
+    );
+
+    assert_eq!(text, "Grüße 🎉 Text");
+}
+
+#[test]
+fn ranged_change_uses_utf32_columns() {
+    let mut text = String::from("Grüße 🎉 text");
+
+    apply_content_changes(
+        &mut text,
+        &[ranged_change((0, 8), (0, 12), "Text")],
+        PositionEncoding::Utf32,
+    );
+
+    assert_eq!(text, "Grüße 🎉 Text");
+}
+
+#[test]
+fn change_after_line_end_is_clamped() {
+    let mut text = String::from("first\nsecond");
+
+    apply_content_changes(
+        &mut text,
+        &[ranged_change((0, 20), (0, 20), "!")],
+        PositionEncoding::Utf16,
+    );
+
+    assert_eq!(text, "first!\nsecond");
+}
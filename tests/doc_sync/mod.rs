@@ -0,0 +1,2 @@
+mod content_changes;
+mod worker;
@@ -0,0 +1,286 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use lsp_types::{
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem, Url,
+    VersionedTextDocumentIdentifier,
+};
+use tokio::sync::mpsc;
+use unimarkup_lsp::doc_sync::{DocChangeWorker, DocEvent, DocUpdate};
+use unimarkup_lsp::line_index::PositionEncoding;
+use unimarkup_lsp::settings::Settings;
+
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+fn start_worker() -> (mpsc::UnboundedSender<DocEvent>, mpsc::Receiver<DocUpdate>) {
+    let (tx_um, rx_um) = mpsc::channel(10);
+    let (tx_doc_event, rx_doc_event) = mpsc::unbounded_channel();
+    let settings = Settings {
+        compile_debounce_ms: Some(DEBOUNCE.as_millis() as u64),
+        ..Default::default()
+    };
+
+    DocChangeWorker::init(tx_um, rx_doc_event, &settings, PositionEncoding::Utf16);
+
+    (tx_doc_event, rx_um)
+}
+
+fn doc_uri() -> Url {
+    // Untitled documents have no file on disk
+    Url::parse("untitled:Untitled-1").unwrap()
+}
+
+fn open(version: i32, text: &str) -> DocEvent {
+    DocEvent::Open(DidOpenTextDocumentParams {
+        text_document: TextDocumentItem::new(
+            doc_uri(),
+            "unimarkup".to_string(),
+            version,
+            text.to_string(),
+        ),
+    })
+}
+
+fn change(version: i32, text: &str) -> DocEvent {
+    DocEvent::Change(DidChangeTextDocumentParams {
+        text_document: VersionedTextDocumentIdentifier::new(doc_uri(), version),
+        content_changes: vec![TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: text.to_string(),
+        }],
+    })
+}
+
+fn close() -> DocEvent {
+    DocEvent::Close(DidCloseTextDocumentParams {
+        text_document: TextDocumentIdentifier::new(doc_uri()),
+    })
+}
+
+/// Returns the document version of a compilation result.
+fn compiled_version(update: DocUpdate) -> Option<i32> {
+    match update {
+        DocUpdate::Compiled(um_doc) => um_doc.version,
+        DocUpdate::Failed { version, .. } => version,
+        DocUpdate::Closed(_) | DocUpdate::Removed(_) => panic!("Expected a compilation result."),
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn compile_results_never_go_backwards() {
+    let (tx_doc_event, mut rx_um) = start_worker();
+    let last_version = 20;
+
+    tx_doc_event.send(open(1, "# heading")).unwrap();
+    for version in 2..=last_version {
+        // Changes arrive while the previous version is compiled
+        tokio::time::sleep(DEBOUNCE).await;
+        tx_doc_event
+            .send(change(version, &format!("# heading {}", version)))
+            .unwrap();
+    }
+
+    let mut versions = Vec::new();
+    while versions.last() != Some(&last_version) {
+        let version = compiled_version(rx_um.recv().await.unwrap()).unwrap();
+        versions.push(version);
+    }
+
+    assert!(versions.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[tokio::test(start_paused = true)]
+async fn closed_document_sends_closed_update() {
+    let (tx_doc_event, mut rx_um) = start_worker();
+
+    tx_doc_event.send(open(1, "# heading")).unwrap();
+    assert_eq!(compiled_version(rx_um.recv().await.unwrap()), Some(1));
+
+    tx_doc_event.send(close()).unwrap();
+
+    assert!(matches!(rx_um.recv().await, Some(DocUpdate::Closed(uri)) if uri == doc_uri()));
+}
+
+#[tokio::test(start_paused = true)]
+async fn closed_document_is_not_compiled() {
+    let (tx_doc_event, mut rx_um) = start_worker();
+
+    tx_doc_event.send(open(1, "# heading")).unwrap();
+    assert_eq!(compiled_version(rx_um.recv().await.unwrap()), Some(1));
+
+    tx_doc_event.send(change(2, "# changed heading")).unwrap();
+    tx_doc_event.send(close()).unwrap();
+
+    assert!(matches!(rx_um.recv().await, Some(DocUpdate::Closed(uri)) if uri == doc_uri()));
+    assert!(tokio::time::timeout(DEBOUNCE * 10, rx_um.recv())
+        .await
+        .is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn opened_document_is_compiled_without_debounce() {
+    let (tx_doc_event, mut rx_um) = start_worker();
+    let start = tokio::time::Instant::now();
+
+    tx_doc_event.send(open(1, "# heading")).unwrap();
+
+    assert_eq!(compiled_version(rx_um.recv().await.unwrap()), Some(1));
+    assert!(start.elapsed() < DEBOUNCE);
+}
+
+#[tokio::test(start_paused = true)]
+async fn changes_within_debounce_window_are_compiled_once() {
+    let (tx_doc_event, mut rx_um) = start_worker();
+
+    tx_doc_event.send(open(1, "# heading")).unwrap();
+    assert_eq!(compiled_version(rx_um.recv().await.unwrap()), Some(1));
+
+    let start = tokio::time::Instant::now();
+    tx_doc_event.send(change(2, "# heading 2")).unwrap();
+    tx_doc_event.send(change(3, "# heading 3")).unwrap();
+    tx_doc_event.send(change(4, "# heading 4")).unwrap();
+
+    assert_eq!(compiled_version(rx_um.recv().await.unwrap()), Some(4));
+    assert!(start.elapsed() >= DEBOUNCE);
+    assert!(tokio::time::timeout(DEBOUNCE * 10, rx_um.recv())
+        .await
+        .is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn change_restarts_debounce_window() {
+    let (tx_doc_event, mut rx_um) = start_worker();
+
+    tx_doc_event.send(open(1, "# heading")).unwrap();
+    assert_eq!(compiled_version(rx_um.recv().await.unwrap()), Some(1));
+
+    let start = tokio::time::Instant::now();
+    tx_doc_event.send(change(2, "# heading 2")).unwrap();
+    tokio::time::sleep(DEBOUNCE / 2).await;
+    tx_doc_event.send(change(3, "# heading 3")).unwrap();
+
+    assert_eq!(compiled_version(rx_um.recv().await.unwrap()), Some(3));
+    assert!(start.elapsed() >= DEBOUNCE + DEBOUNCE / 2);
+}
+
+#[tokio::test]
+async fn indexed_document_is_recompiled_on_reconfigure() {
+    let workspace_dir =
+        std::env::temp_dir().join(format!("um-lsp-reconfigure-{}", std::process::id()));
+    std::fs::create_dir_all(&workspace_dir).unwrap();
+
+    let um_file = workspace_dir.join("document.um");
+    std::fs::write(&um_file, "# heading").unwrap();
+    let uri = Url::from_file_path(&um_file).unwrap();
+
+    let (tx_doc_event, mut rx_um) = start_worker();
+
+    tx_doc_event.send(DocEvent::Index(uri.clone())).unwrap();
+    let indexed = rx_um.recv().await.unwrap();
+
+    tx_doc_event
+        .send(DocEvent::Reconfigure(Settings::default()))
+        .unwrap();
+    let reindexed = rx_um.recv().await.unwrap();
+
+    std::fs::remove_dir_all(&workspace_dir).unwrap();
+
+    assert_eq!(compiled_version(indexed), None);
+    assert_eq!(compiled_version(reindexed), None);
+}
+
+#[tokio::test]
+async fn unreadable_indexed_file_is_forwarded_as_failed() {
+    let um_file = std::env::temp_dir().join(format!("um-lsp-missing-{}.um", std::process::id()));
+    let uri = Url::from_file_path(&um_file).unwrap();
+
+    let (tx_doc_event, mut rx_um) = start_worker();
+
+    tx_doc_event.send(DocEvent::Index(uri.clone())).unwrap();
+
+    assert!(matches!(
+        rx_um.recv().await,
+        Some(DocUpdate::Failed { uri: failed_uri, version: None, .. }) if failed_uri == uri
+    ));
+}
+
+#[tokio::test]
+async fn closed_document_is_indexed_from_disk() {
+    let workspace_dir =
+        std::env::temp_dir().join(format!("um-lsp-close-index-{}", std::process::id()));
+    std::fs::create_dir_all(&workspace_dir).unwrap();
+
+    let um_file = workspace_dir.join("document.um");
+    std::fs::write(&um_file, "# On disk").unwrap();
+    let uri = Url::from_file_path(&um_file).unwrap();
+
+    let (tx_doc_event, mut rx_um) = start_worker();
+
+    tx_doc_event
+        .send(DocEvent::Open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(
+                uri.clone(),
+                "unimarkup".to_string(),
+                1,
+                "# Opened".to_string(),
+            ),
+        }))
+        .unwrap();
+    let opened = rx_um.recv().await.unwrap();
+
+    tx_doc_event
+        .send(DocEvent::Close(DidCloseTextDocumentParams {
+            text_document: TextDocumentIdentifier::new(uri.clone()),
+        }))
+        .unwrap();
+    tx_doc_event.send(DocEvent::Index(uri.clone())).unwrap();
+    let closed = rx_um.recv().await.unwrap();
+    let indexed = rx_um.recv().await.unwrap();
+
+    std::fs::remove_dir_all(&workspace_dir).unwrap();
+
+    assert_eq!(compiled_version(opened), Some(1));
+    assert!(matches!(closed, DocUpdate::Closed(closed_uri) if closed_uri == uri));
+    assert_eq!(compiled_version(indexed), None);
+}
+
+#[tokio::test(start_paused = true)]
+async fn untitled_document_is_compiled_without_um_file() {
+    let (tx_doc_event, mut rx_um) = start_worker();
+
+    tx_doc_event.send(open(1, "# heading")).unwrap();
+
+    match rx_um.recv().await.unwrap() {
+        DocUpdate::Compiled(um_doc) => {
+            assert_eq!(um_doc.uri, doc_uri());
+            assert_eq!(um_doc.doc.config.um_file, PathBuf::new());
+        }
+        _ => panic!("Expected the untitled document to compile."),
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn file_document_is_compiled_with_um_file() {
+    let um_file = std::env::temp_dir().join("document.um");
+    let uri = Url::from_file_path(&um_file).unwrap();
+
+    let (tx_doc_event, mut rx_um) = start_worker();
+
+    tx_doc_event
+        .send(DocEvent::Open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(
+                uri,
+                "unimarkup".to_string(),
+                1,
+                "# heading".to_string(),
+            ),
+        }))
+        .unwrap();
+
+    match rx_um.recv().await.unwrap() {
+        DocUpdate::Compiled(um_doc) => assert_eq!(um_doc.doc.config.um_file, um_file),
+        _ => panic!("Expected the document to compile."),
+    }
+}
@@ -0,0 +1,112 @@
+use lsp_types::{Position, Range, Url};
+use unimarkup_core::config::Config;
+use unimarkup_lsp::document_symbols::{flatten_symbols, get_document_symbols};
+use unimarkup_lsp::line_index::{LineIndex, PositionEncoding};
+
+#[test]
+fn heading_symbol_covers_its_section() {
+    let input = "# Heading\n\nFirst paragraph\n\nSecond paragraph\n";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let symbols = get_document_symbols(&document, &line_index);
+
+    assert_eq!(symbols.len(), 1);
+    assert_eq!(symbols[0].name, "Heading");
+    assert_eq!(
+        symbols[0].range,
+        Range::new(Position::new(0, 0), Position::new(4, 16))
+    );
+    assert_eq!(
+        symbols[0].selection_range,
+        Range::new(Position::new(0, 2), Position::new(0, 9))
+    );
+}
+
+#[test]
+fn heading_selection_starts_at_text_after_multiple_spaces() {
+    let input = "##   Heading";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let symbols = get_document_symbols(&document, &line_index);
+
+    assert_eq!(
+        symbols[0].selection_range,
+        Range::new(Position::new(0, 5), Position::new(0, 12))
+    );
+}
+
+#[test]
+fn headings_are_nested_by_level() {
+    let input = "# First\n\n## Sub\n\ntext\n\n### Subsub\n\n## Other sub\n\n# Second";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let symbols = get_document_symbols(&document, &line_index);
+
+    let names: Vec<_> = symbols.iter().map(|symbol| symbol.name.as_str()).collect();
+    assert_eq!(names, ["First", "Second"]);
+
+    let sub_symbols = symbols[0].children.as_ref().unwrap();
+    let sub_names: Vec<_> = sub_symbols
+        .iter()
+        .map(|symbol| symbol.name.as_str())
+        .collect();
+    assert_eq!(sub_names, ["Sub", "Other sub"]);
+
+    // The section of "Sub" ends before "Other sub" and includes its subsection
+    assert_eq!(
+        sub_symbols[0].range,
+        Range::new(Position::new(2, 0), Position::new(6, 10))
+    );
+    assert_eq!(sub_symbols[0].children.as_ref().unwrap()[0].name, "Subsub");
+
+    assert_eq!(
+        symbols[0].range,
+        Range::new(Position::new(0, 0), Position::new(8, 12))
+    );
+    assert!(symbols[1].children.is_none());
+}
+
+#[test]
+fn heading_with_skipped_level_is_nested_in_previous_heading() {
+    let input = "# First\n\n### Deep\n\n## Sub";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let symbols = get_document_symbols(&document, &line_index);
+
+    let children = symbols[0].children.as_ref().unwrap();
+    let names: Vec<_> = children.iter().map(|symbol| symbol.name.as_str()).collect();
+    assert_eq!(names, ["Deep", "Sub"]);
+}
+
+#[test]
+fn flat_symbols_keep_parent_as_container() {
+    let input = "# First\n\n## Sub\n\n# Second";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+    let uri = Url::parse("file:///doc.um").unwrap();
+
+    let symbols = flatten_symbols(&uri, get_document_symbols(&document, &line_index));
+
+    let names: Vec<_> = symbols
+        .iter()
+        .map(|symbol| (symbol.name.as_str(), symbol.container_name.as_deref()))
+        .collect();
+    assert_eq!(
+        names,
+        [("First", None), ("Sub", Some("First")), ("Second", None)]
+    );
+    assert_eq!(symbols[1].location.uri, uri);
+}
+
+#[test]
+fn document_without_headings_has_no_symbols() {
+    let input = "Only a paragraph";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    assert!(get_document_symbols(&document, &line_index).is_empty());
+}
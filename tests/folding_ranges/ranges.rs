@@ -0,0 +1,101 @@
+use lsp_types::{FoldingRange, FoldingRangeKind};
+use unimarkup_core::config::Config;
+use unimarkup_lsp::folding_ranges::{get_folding_ranges, FoldingSupport};
+use unimarkup_lsp::line_index::{LineIndex, PositionEncoding};
+
+fn folding_ranges(input: &str, support: FoldingSupport) -> Vec<FoldingRange> {
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    get_folding_ranges(&document, &line_index, support)
+}
+
+fn line_range(start_line: u32, end_line: u32) -> FoldingRange {
+    FoldingRange {
+        start_line,
+        end_line,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn sections_fold_to_next_heading_of_same_level() {
+    let input = "# First\n\ntext\n\n## Sub\n\ntext\n\n# Second\n\ntext";
+
+    let ranges = folding_ranges(input, FoldingSupport::default());
+
+    assert_eq!(
+        ranges,
+        [line_range(0, 6), line_range(4, 6), line_range(8, 10)]
+    );
+}
+
+#[test]
+fn verbatim_folds_from_fence_to_fence() {
+    let input = "```rust\nfn main() {}\n\nlet x = 1;\n```";
+
+    let ranges = folding_ranges(input, FoldingSupport::default());
+
+    assert_eq!(ranges, [line_range(0, 4)]);
+}
+
+#[test]
+fn multiline_inline_folds_between_delimiters() {
+    let input = "plain **bold\ntext** after";
+
+    let ranges = folding_ranges(input, FoldingSupport::default());
+
+    assert_eq!(
+        ranges,
+        [FoldingRange {
+            start_line: 0,
+            start_character: Some(6),
+            end_line: 1,
+            end_character: Some(6),
+            ..Default::default()
+        }]
+    );
+}
+
+#[test]
+fn multiline_inline_keeps_end_line_without_character_support() {
+    let input = "**bold\ntext\nover lines** after";
+    let support = FoldingSupport {
+        line_folding_only: true,
+        ..Default::default()
+    };
+
+    let ranges = folding_ranges(input, support);
+
+    assert_eq!(ranges, [line_range(0, 1)]);
+}
+
+#[test]
+fn comment_runs_have_comment_kind() {
+    let input = ";; first comment\n;; second comment\n\n;; single comment";
+
+    let ranges = folding_ranges(input, FoldingSupport::default());
+
+    assert_eq!(
+        ranges,
+        [FoldingRange {
+            start_line: 0,
+            end_line: 1,
+            kind: Some(FoldingRangeKind::Comment),
+            ..Default::default()
+        }]
+    );
+}
+
+#[test]
+fn range_limit_drops_later_ranges() {
+    let input = "# First\n\ntext\n\n# Second\n\ntext\n\n# Third\n\ntext";
+    let support = FoldingSupport {
+        range_limit: Some(2),
+        ..Default::default()
+    };
+
+    let ranges = folding_ranges(input, support);
+
+    assert_eq!(ranges, [line_range(0, 2), line_range(4, 6)]);
+}
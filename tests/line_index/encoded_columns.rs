@@ -0,0 +1,117 @@
+use lsp_types::{ClientCapabilities, GeneralClientCapabilities, Position, PositionEncodingKind};
+use unimarkup_lsp::line_index::{LineIndex, PositionEncoding};
+
+#[test]
+fn ascii_columns_are_equal_in_all_encodings() {
+    for encoding in [
+        PositionEncoding::Utf8,
+        PositionEncoding::Utf16,
+        PositionEncoding::Utf32,
+    ] {
+        let line_index = LineIndex::new("# heading\nplain text", encoding);
+
+        assert_eq!(line_index.encoded_column(1, 6), 6);
+        assert_eq!(line_index.encoded_len(1, 6, 4), 4);
+    }
+}
+
+#[test]
+fn multibyte_columns_are_converted() {
+    let text = "# heading\nGrüße 🎉 text";
+
+    let utf8_index = LineIndex::new(text, PositionEncoding::Utf8);
+    let utf16_index = LineIndex::new(text, PositionEncoding::Utf16);
+    let utf32_index = LineIndex::new(text, PositionEncoding::Utf32);
+
+    // Column of "text" counted in characters
+    assert_eq!(utf8_index.encoded_column(1, 8), 13);
+    assert_eq!(utf16_index.encoded_column(1, 8), 9);
+    assert_eq!(utf32_index.encoded_column(1, 8), 8);
+
+    // Length of "Grüße 🎉" counted in characters
+    assert_eq!(utf8_index.encoded_len(1, 0, 7), 12);
+    assert_eq!(utf16_index.encoded_len(1, 0, 7), 8);
+    assert_eq!(utf32_index.encoded_len(1, 0, 7), 7);
+}
+
+#[test]
+fn columns_after_line_end_are_counted_as_characters() {
+    let line_index = LineIndex::new("äö\r\nsecond", PositionEncoding::Utf8);
+
+    assert_eq!(line_index.line(0), "äö");
+    assert_eq!(line_index.encoded_column(0, 4), 6);
+}
+
+#[test]
+fn first_supported_client_encoding_is_negotiated() {
+    let capabilities = ClientCapabilities {
+        general: Some(GeneralClientCapabilities {
+            position_encodings: Some(vec![
+                PositionEncodingKind::new("utf-7"),
+                PositionEncodingKind::UTF8,
+                PositionEncodingKind::UTF16,
+            ]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        PositionEncoding::negotiate(&capabilities),
+        PositionEncoding::Utf8
+    );
+}
+
+#[test]
+fn utf16_is_negotiated_without_client_encodings() {
+    assert_eq!(
+        PositionEncoding::negotiate(&ClientCapabilities::default()),
+        PositionEncoding::Utf16
+    );
+}
+
+#[test]
+fn line_end_is_encoded() {
+    let line_index = LineIndex::new("# heading\r\nGrüße 🎉\n", PositionEncoding::Utf16);
+
+    assert_eq!(line_index.line_end(0), Position::new(0, 9));
+    assert_eq!(line_index.line_end(1), Position::new(1, 8));
+    assert_eq!(line_index.line_end(2), Position::new(2, 0));
+}
+
+#[test]
+fn encoded_columns_are_converted_back_to_characters() {
+    let text = "# heading\nGrüße 🎉 text";
+
+    let utf8_index = LineIndex::new(text, PositionEncoding::Utf8);
+    let utf16_index = LineIndex::new(text, PositionEncoding::Utf16);
+
+    assert_eq!(utf8_index.char_column(1, 13), 8);
+    assert_eq!(utf16_index.char_column(1, 9), 8);
+    // Columns after the line end
+    assert_eq!(utf16_index.char_column(0, 12), 12);
+}
+
+#[test]
+fn line_break_len_is_taken_from_text() {
+    let line_index = LineIndex::new("first\r\nsecond\nlast", PositionEncoding::Utf16);
+
+    assert_eq!(line_index.line(0), "first");
+    assert_eq!(line_index.line_break_len(0), 2);
+    assert_eq!(line_index.line_break_len(1), 1);
+    assert_eq!(line_index.line_break_len(2), 0);
+}
+
+#[test]
+fn multiline_len_is_encoded_per_line() {
+    let text = "ab\n🎉 c\r\nd";
+
+    let utf8_index = LineIndex::new(text, PositionEncoding::Utf8);
+    let utf16_index = LineIndex::new(text, PositionEncoding::Utf16);
+    let utf32_index = LineIndex::new(text, PositionEncoding::Utf32);
+
+    // Length of "b\n🎉 c\r\nd" counted in characters
+    assert_eq!(utf8_index.encoded_len(0, 1, 8), 11);
+    assert_eq!(utf16_index.encoded_len(0, 1, 8), 9);
+    assert_eq!(utf32_index.encoded_len(0, 1, 8), 8);
+}
@@ -0,0 +1 @@
+mod encoded_columns;
@@ -1 +1,12 @@
+mod definitions;
+mod diagnostics;
+mod doc_sync;
+mod document_symbols;
+mod folding_ranges;
+mod line_index;
+mod requests;
+mod selection_ranges;
 mod semantic_tokens;
+mod settings;
+mod workspace;
+mod workspace_symbols;
@@ -0,0 +1,2 @@
+mod pending_requests;
+mod refresh_throttle;
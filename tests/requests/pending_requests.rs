@@ -0,0 +1,73 @@
+use lsp_server::{ErrorCode, Response};
+use unimarkup_lsp::requests::{PendingRequests, ServerRequest};
+
+#[test]
+fn created_requests_have_unique_ids() {
+    let mut pending_requests = PendingRequests::default();
+
+    let first = pending_requests.create(
+        ServerRequest::SemanticTokensRefresh,
+        serde_json::Value::Null,
+    );
+    let second = pending_requests.create(
+        ServerRequest::SemanticTokensRefresh,
+        serde_json::Value::Null,
+    );
+
+    assert_ne!(first.id, second.id);
+    assert_eq!(first.method, "workspace/semanticTokens/refresh");
+}
+
+#[test]
+fn response_completes_its_request() {
+    let mut pending_requests = PendingRequests::default();
+    let request = pending_requests.create(ServerRequest::Configuration, serde_json::Value::Null);
+
+    let response = Response::new_ok(request.id, serde_json::json!([{}]));
+
+    assert_eq!(
+        pending_requests.complete(&response),
+        Some(ServerRequest::Configuration)
+    );
+    assert_eq!(pending_requests.complete(&response), None);
+}
+
+#[test]
+fn error_response_completes_its_request() {
+    let mut pending_requests = PendingRequests::default();
+    let request = pending_requests.create(
+        ServerRequest::SemanticTokensRefresh,
+        serde_json::Value::Null,
+    );
+
+    let response = Response::new_err(
+        request.id,
+        ErrorCode::InternalError as i32,
+        "refresh failed".to_string(),
+    );
+
+    assert_eq!(
+        pending_requests.complete(&response),
+        Some(ServerRequest::SemanticTokensRefresh)
+    );
+}
+
+#[test]
+fn response_to_unknown_request_is_ignored() {
+    let mut pending_requests = PendingRequests::default();
+    pending_requests.create(ServerRequest::Configuration, serde_json::Value::Null);
+
+    let response = Response::new_ok("doc-update-1".to_string().into(), serde_json::Value::Null);
+
+    assert_eq!(pending_requests.complete(&response), None);
+}
+
+#[test]
+fn diagnostic_refresh_request_method() {
+    let mut pending_requests = PendingRequests::default();
+
+    let request =
+        pending_requests.create(ServerRequest::DiagnosticRefresh, serde_json::Value::Null);
+
+    assert_eq!(request.method, "workspace/diagnostic/refresh");
+}
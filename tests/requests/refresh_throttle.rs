@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use tokio::time::Instant;
+use unimarkup_lsp::requests::{RefreshThrottle, REFRESH_RESPONSE_TIMEOUT};
+
+const INTERVAL: Duration = Duration::from_millis(500);
+
+#[test]
+fn no_refresh_without_request() {
+    let throttle = RefreshThrottle::new(INTERVAL);
+
+    assert_eq!(throttle.next_refresh(Instant::now()), None);
+}
+
+#[test]
+fn first_refresh_is_sent_immediately() {
+    let mut throttle = RefreshThrottle::new(INTERVAL);
+    let now = Instant::now();
+
+    throttle.request();
+
+    assert_eq!(throttle.next_refresh(now), Some(now));
+}
+
+#[test]
+fn burst_of_requests_results_in_one_refresh() {
+    let mut throttle = RefreshThrottle::new(INTERVAL);
+    let start = Instant::now();
+
+    throttle.request();
+    throttle.sent(start);
+    throttle.answered();
+
+    for _ in 0..20 {
+        throttle.request();
+    }
+
+    assert_eq!(
+        throttle.next_refresh(start + Duration::from_millis(100)),
+        Some(start + INTERVAL)
+    );
+
+    throttle.sent(start + INTERVAL);
+    throttle.answered();
+
+    assert_eq!(throttle.next_refresh(start + INTERVAL), None);
+}
+
+#[test]
+fn unanswered_refresh_delays_next_refresh() {
+    let mut throttle = RefreshThrottle::new(INTERVAL);
+    let start = Instant::now();
+
+    throttle.request();
+    throttle.sent(start);
+    throttle.request();
+
+    assert_eq!(
+        throttle.next_refresh(start + INTERVAL),
+        Some(start + REFRESH_RESPONSE_TIMEOUT)
+    );
+
+    throttle.answered();
+
+    assert_eq!(
+        throttle.next_refresh(start + INTERVAL),
+        Some(start + INTERVAL)
+    );
+}
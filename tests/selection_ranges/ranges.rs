@@ -0,0 +1,131 @@
+use lsp_types::{Position, Range, SelectionRange};
+use unimarkup_core::config::Config;
+use unimarkup_lsp::line_index::{LineIndex, PositionEncoding};
+use unimarkup_lsp::selection_ranges::get_selection_range;
+
+fn selection_ranges(input: &str, position: Position) -> Vec<Range> {
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let mut ranges = Vec::new();
+    let mut selection_range: Option<SelectionRange> =
+        Some(get_selection_range(&document, &line_index, position));
+
+    while let Some(range) = selection_range {
+        ranges.push(range.range);
+        selection_range = range.parent.map(|parent| *parent);
+    }
+
+    ranges
+}
+
+fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+    Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1))
+}
+
+#[test]
+fn selection_grows_from_word_to_document() {
+    let input = "# Heading\n\nplain **bold text** after\n\n# Other";
+
+    let ranges = selection_ranges(input, Position::new(2, 9));
+
+    assert_eq!(
+        ranges,
+        [
+            // Word
+            range((2, 8), (2, 12)),
+            // Bold content
+            range((2, 8), (2, 17)),
+            // Bold with delimiters
+            range((2, 6), (2, 19)),
+            // Paragraph
+            range((2, 0), (2, 25)),
+            // Section
+            range((0, 0), (2, 25)),
+            // Document
+            range((0, 0), (4, 7)),
+        ]
+    );
+}
+
+#[test]
+fn nested_inlines_are_selected_from_inside_out() {
+    let input = "**bold *italic* text**";
+
+    let ranges = selection_ranges(input, Position::new(0, 10));
+
+    assert_eq!(
+        ranges,
+        [
+            range((0, 8), (0, 14)),
+            range((0, 7), (0, 15)),
+            range((0, 2), (0, 20)),
+            range((0, 0), (0, 22)),
+        ]
+    );
+}
+
+#[test]
+fn verbatim_content_is_selected_before_fences() {
+    let input = "```\nfirst line\nsecond line\n```";
+
+    let ranges = selection_ranges(input, Position::new(1, 2));
+
+    assert_eq!(
+        ranges,
+        [
+            range((1, 0), (1, 5)),
+            range((1, 0), (2, 11)),
+            range((0, 0), (3, 3)),
+        ]
+    );
+}
+
+#[test]
+fn selection_outside_blocks_covers_document() {
+    let input = "first\n\n\n\nlast";
+
+    let ranges = selection_ranges(input, Position::new(2, 0));
+
+    assert_eq!(ranges, [range((0, 0), (4, 4))]);
+}
+
+#[test]
+fn nested_entry_inlines_are_selected_after_keyword() {
+    let input = "- first\n  - **nested** entry";
+
+    let ranges = selection_ranges(input, Position::new(1, 8));
+
+    assert_eq!(
+        ranges,
+        [
+            // Bold content
+            range((1, 6), (1, 12)),
+            // Bold with delimiters
+            range((1, 4), (1, 14)),
+            // Nested entry
+            range((1, 0), (1, 20)),
+            // Outer list
+            range((0, 0), (1, 20)),
+        ]
+    );
+}
+
+#[test]
+fn heading_inlines_are_selected_after_multiple_spaces() {
+    let input = "#   **Heading** text";
+
+    let ranges = selection_ranges(input, Position::new(0, 8));
+
+    assert_eq!(
+        ranges,
+        [
+            // Bold content
+            range((0, 6), (0, 13)),
+            // Bold with delimiters
+            range((0, 4), (0, 15)),
+            // Heading
+            range((0, 0), (0, 20)),
+        ]
+    );
+}
@@ -1,13 +1,45 @@
 use insta::assert_snapshot;
+use lsp_types::{SemanticToken, SemanticTokenType};
 use unimarkup_core::config::Config;
-use unimarkup_lsp::semantic_tokens::get_semantic_tokens;
+use unimarkup_lsp::line_index::{LineIndex, PositionEncoding};
+use unimarkup_lsp::semantic_tokens::{
+    get_semantic_tokens,
+    legend::{create_semantic_token_legend, TokenType},
+    SemanticTokensSupport,
+};
+
+/// Returns the absolute line, column, length and type name of the delta encoded tokens.
+fn positioned_tokens(tokens: &[SemanticToken]) -> Vec<(u32, u32, u32, SemanticTokenType)> {
+    let legend = create_semantic_token_legend();
+    let mut line = 0;
+    let mut column = 0;
+
+    tokens
+        .iter()
+        .map(|token| {
+            if token.delta_line > 0 {
+                column = 0;
+            }
+            line += token.delta_line;
+            column += token.delta_start;
+
+            (
+                line,
+                column,
+                token.length,
+                legend.token_types[token.token_type as usize].clone(),
+            )
+        })
+        .collect()
+}
 
 #[test]
 fn heading_token_type() {
     let input = "# heading";
     let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
 
-    let tokens = get_semantic_tokens(&document);
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
 
     assert_snapshot!(format!("{:#?}", tokens));
 }
@@ -16,8 +48,179 @@ fn heading_token_type() {
 fn heading_token_type_with_bold_token_modifier() {
     let input = "# **heading**";
     let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
 
-    let tokens = get_semantic_tokens(&document);
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
 
     assert_snapshot!(format!("{:#?}", tokens));
 }
+
+#[test]
+fn heading_content_after_multiple_spaces() {
+    let input = "#   **heading**";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_eq!(
+        positioned_tokens(&tokens),
+        vec![
+            (0, 0, 1, TokenType::Heading.name()),
+            (0, 4, 2, TokenType::Delimiter.name()),
+            (0, 6, 7, TokenType::Paragraph.name()),
+            (0, 13, 2, TokenType::Delimiter.name()),
+        ]
+    );
+}
+
+#[test]
+fn paragraph_block_without_format_has_no_tokens() {
+    let input = "plain paragraph text";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert!(tokens.is_empty());
+}
+
+#[test]
+fn verbatim_block_has_verbatim_tokens() {
+    let input = "```\nverbatim text\n```";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+    let legend = create_semantic_token_legend();
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert!(tokens
+        .iter()
+        .any(|token| legend.token_types[token.token_type as usize] == TokenType::Verbatim.name()));
+}
+
+#[test]
+fn bullet_list_block_has_entry_delimiter_tokens() {
+    let input = "- first entry\n- second entry";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+    let legend = create_semantic_token_legend();
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    let delimiter_cnt = tokens
+        .iter()
+        .filter(|token| {
+            legend.token_types[token.token_type as usize] == TokenType::Delimiter.name()
+        })
+        .count();
+    assert_eq!(delimiter_cnt, 2);
+}
+
+#[test]
+fn verbatim_fence_and_language_tokens() {
+    let input = "```rust\nlet x = 1;\n```";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn verbatim_long_fence_with_indented_content() {
+    let input = "````` python\n  print()\n`````";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn verbatim_with_known_language_is_highlighted() {
+    let input = "```rust\nfn main() {}\n```";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+    let legend = create_semantic_token_legend();
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert!(tokens
+        .iter()
+        .any(|token| legend.token_types[token.token_type as usize] == TokenType::Keyword.name()));
+    assert!(tokens
+        .iter()
+        .any(|token| legend.token_types[token.token_type as usize] == TokenType::Function.name()));
+}
+
+#[test]
+fn verbatim_with_unknown_language_is_not_highlighted() {
+    let input = "```unknown-language\nfn main() {}\n```";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn nested_bullet_list_entry_tokens_start_at_keyword() {
+    let input = "- first entry\n  - **nested** entry";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_eq!(
+        positioned_tokens(&tokens),
+        vec![
+            (0, 0, 1, TokenType::Delimiter.name()),
+            (1, 2, 1, TokenType::Delimiter.name()),
+            (1, 4, 2, TokenType::Delimiter.name()),
+            (1, 6, 6, TokenType::Paragraph.name()),
+            (1, 12, 2, TokenType::Delimiter.name()),
+        ]
+    );
+}
+
+#[test]
+fn paragraph_inside_bullet_list_entry() {
+    let input = "- entry\n\n  **bold** paragraph";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_eq!(
+        positioned_tokens(&tokens),
+        vec![
+            (0, 0, 1, TokenType::Delimiter.name()),
+            (2, 2, 2, TokenType::Delimiter.name()),
+            (2, 4, 4, TokenType::Paragraph.name()),
+            (2, 8, 2, TokenType::Delimiter.name()),
+        ]
+    );
+}
+
+#[test]
+fn verbatim_inside_bullet_list_entry() {
+    let input = "- entry\n\n  ```\n  code\n  ```";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_eq!(
+        positioned_tokens(&tokens),
+        vec![
+            (0, 0, 1, TokenType::Delimiter.name()),
+            (2, 2, 3, TokenType::Delimiter.name()),
+            (3, 2, 4, TokenType::Verbatim.name()),
+            (4, 2, 3, TokenType::Delimiter.name()),
+        ]
+    );
+}
@@ -0,0 +1,94 @@
+use lsp_types::{SemanticToken, SemanticTokensEdit};
+use unimarkup_lsp::semantic_tokens::get_semantic_tokens_edits;
+
+fn token(delta_line: u32, delta_start: u32, length: u32) -> SemanticToken {
+    SemanticToken {
+        delta_line,
+        delta_start,
+        length,
+        token_type: 0,
+        token_modifiers_bitset: 1,
+    }
+}
+
+#[test]
+fn unchanged_tokens_have_no_edits() {
+    let tokens = vec![token(0, 0, 2), token(0, 2, 9), token(0, 9, 2)];
+
+    let edits = get_semantic_tokens_edits(&tokens, &tokens);
+
+    assert!(edits.is_empty());
+}
+
+#[test]
+fn changed_token_is_replaced() {
+    let previous = vec![token(0, 0, 2), token(0, 2, 9), token(0, 9, 2)];
+    let current = vec![token(0, 0, 2), token(0, 2, 12), token(0, 12, 2)];
+
+    let edits = get_semantic_tokens_edits(&previous, &current);
+
+    assert_eq!(
+        edits,
+        vec![SemanticTokensEdit {
+            start: 5,
+            delete_count: 10,
+            data: Some(vec![token(0, 2, 12), token(0, 12, 2)]),
+        }]
+    );
+}
+
+#[test]
+fn appended_tokens_are_inserted() {
+    let previous = vec![token(0, 0, 2), token(0, 2, 9), token(0, 9, 2)];
+    let current = vec![
+        token(0, 0, 2),
+        token(0, 2, 9),
+        token(0, 9, 2),
+        token(2, 0, 2),
+    ];
+
+    let edits = get_semantic_tokens_edits(&previous, &current);
+
+    assert_eq!(
+        edits,
+        vec![SemanticTokensEdit {
+            start: 15,
+            delete_count: 0,
+            data: Some(vec![token(2, 0, 2)]),
+        }]
+    );
+}
+
+#[test]
+fn removed_tokens_are_deleted() {
+    let previous = vec![token(0, 0, 2), token(0, 2, 9), token(0, 9, 2)];
+    let current = vec![token(0, 0, 2)];
+
+    let edits = get_semantic_tokens_edits(&previous, &current);
+
+    assert_eq!(
+        edits,
+        vec![SemanticTokensEdit {
+            start: 5,
+            delete_count: 10,
+            data: None,
+        }]
+    );
+}
+
+#[test]
+fn repeated_tokens_are_not_matched_twice() {
+    let previous = vec![token(0, 0, 2), token(0, 2, 2)];
+    let current = vec![token(0, 0, 2), token(0, 2, 2), token(0, 2, 2)];
+
+    let edits = get_semantic_tokens_edits(&previous, &current);
+
+    assert_eq!(
+        edits,
+        vec![SemanticTokensEdit {
+            start: 10,
+            delete_count: 0,
+            data: Some(vec![token(0, 2, 2)]),
+        }]
+    );
+}
@@ -1,13 +1,15 @@
 use insta::assert_snapshot;
 use unimarkup_core::config::Config;
-use unimarkup_lsp::semantic_tokens::get_semantic_tokens;
+use unimarkup_lsp::line_index::{LineIndex, PositionEncoding};
+use unimarkup_lsp::semantic_tokens::{get_semantic_tokens, SemanticTokensSupport};
 
 #[test]
 fn bold_token_modifier() {
     let input = "**bold text**";
     let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
 
-    let tokens = get_semantic_tokens(&document);
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
 
     assert_snapshot!(format!("{:#?}", tokens));
 }
@@ -16,8 +18,9 @@ fn bold_token_modifier() {
 fn bold_token_modifier_before_plain() {
     let input = "**bold text**plain text";
     let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
 
-    let tokens = get_semantic_tokens(&document);
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
 
     assert_snapshot!(format!("{:#?}", tokens));
 }
@@ -26,8 +29,9 @@ fn bold_token_modifier_before_plain() {
 fn bold_token_modifier_after_plain() {
     let input = "plain text**bold text**";
     let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
 
-    let tokens = get_semantic_tokens(&document);
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
 
     assert_snapshot!(format!("{:#?}", tokens));
 }
@@ -36,8 +40,9 @@ fn bold_token_modifier_after_plain() {
 fn italic_token_modifier() {
     let input = "*italic text*";
     let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
 
-    let tokens = get_semantic_tokens(&document);
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
 
     assert_snapshot!(format!("{:#?}", tokens));
 }
@@ -46,8 +51,185 @@ fn italic_token_modifier() {
 fn bold_italic_token_modifier() {
     let input = "***bold and italic text***";
     let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
 
-    let tokens = get_semantic_tokens(&document);
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn link_target_token_type() {
+    let input = "[text](https://unimarkup.org)";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn underline_token_modifier() {
+    let input = "__underline text__";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn bold_token_modifier_inside_underline() {
+    let input = "__**bold underline**__";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn strikethrough_token_modifier_inside_bold() {
+    let input = "**bold ~~strikethrough~~**";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn subscript_token_modifier_inside_italic() {
+    let input = "*italic _subscript_*";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn superscript_token_modifier_inside_bold() {
+    let input = "**bold ^superscript^**";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn overline_token_modifier_inside_underline() {
+    let input = "__underline ‾overline‾__";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn highlight_token_modifier_inside_italic() {
+    let input = "*italic ||highlight||*";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn quote_token_type_with_bold_token_modifier() {
+    let input = "\"\"quoted **bold** text\"\"";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn math_token_type() {
+    let input = "$a + b$";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn math_token_type_inside_bold() {
+    let input = "**bold $a + b$**";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn substitution_token_type() {
+    let input = "::smile::";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn substitution_token_type_inside_italic() {
+    let input = "*italic ::smile::*";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn bold_token_modifier_with_multibyte_content() {
+    let input = "**Grüße 🎉**";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf8);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn bold_token_modifier_with_multibyte_content_in_utf16() {
+    let input = "**Grüße 🎉**";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn bold_token_modifier_with_multibyte_content_in_utf32() {
+    let input = "**Grüße 🎉**";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf32);
+
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
 
     assert_snapshot!(format!("{:#?}", tokens));
 }
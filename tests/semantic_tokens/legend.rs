@@ -0,0 +1,115 @@
+use lsp_types::{
+    ClientCapabilities, SemanticTokenModifier, SemanticTokenType, SemanticTokensClientCapabilities,
+    TextDocumentClientCapabilities,
+};
+use unimarkup_lsp::semantic_tokens::legend::{
+    create_semantic_token_legend, negotiate_semantic_token_legend, TokenModifier, TokenType,
+};
+
+/// Returns the legend index of the given token type.
+fn index(token_type: TokenType) -> usize {
+    TokenType::ALL
+        .iter()
+        .position(|registered_type| *registered_type == token_type)
+        .unwrap()
+}
+
+fn client_token_types(token_types: Vec<SemanticTokenType>) -> ClientCapabilities {
+    ClientCapabilities {
+        text_document: Some(TextDocumentClientCapabilities {
+            semantic_tokens: Some(SemanticTokensClientCapabilities {
+                token_types,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn legend_is_created_from_token_tables() {
+    let legend = create_semantic_token_legend();
+
+    assert_eq!(legend.token_types.len(), TokenType::ALL.len());
+    assert_eq!(legend.token_modifiers.len(), TokenModifier::ALL.len());
+    assert_eq!(legend.token_types[0], SemanticTokenType::new("paragraph"));
+    assert_eq!(legend.token_types[1], SemanticTokenType::new("heading"));
+    assert_eq!(
+        legend.token_modifiers[0],
+        SemanticTokenModifier::new("bold")
+    );
+    assert_eq!(
+        legend.token_modifiers[1],
+        SemanticTokenModifier::new("italic")
+    );
+}
+
+#[test]
+fn legend_has_no_duplicate_names() {
+    let legend = create_semantic_token_legend();
+
+    for (i, token_type) in legend.token_types.iter().enumerate() {
+        assert!(!legend.token_types[i + 1..].contains(token_type));
+    }
+    for (i, token_modifier) in legend.token_modifiers.iter().enumerate() {
+        assert!(!legend.token_modifiers[i + 1..].contains(token_modifier));
+    }
+}
+
+#[test]
+fn legend_contains_link_type() {
+    let legend = create_semantic_token_legend();
+
+    assert_eq!(
+        legend.token_types[index(TokenType::Link)],
+        SemanticTokenType::new("link")
+    );
+}
+
+#[test]
+fn unknown_unimarkup_types_fall_back_to_standard_types() {
+    let capabilities = client_token_types(vec![
+        SemanticTokenType::KEYWORD,
+        SemanticTokenType::OPERATOR,
+        SemanticTokenType::STRING,
+    ]);
+
+    let legend = negotiate_semantic_token_legend(&capabilities);
+
+    let name = |token_type: TokenType| legend.token_types[index(token_type)].clone();
+    assert_eq!(name(TokenType::Heading), SemanticTokenType::KEYWORD);
+    assert_eq!(name(TokenType::Delimiter), SemanticTokenType::OPERATOR);
+    // Prose is never colored like code
+    assert_eq!(
+        name(TokenType::Paragraph),
+        SemanticTokenType::new("paragraph")
+    );
+    assert_eq!(name(TokenType::Quote), SemanticTokenType::new("quote"));
+}
+
+#[test]
+fn known_unimarkup_types_are_kept() {
+    let capabilities = client_token_types(vec![
+        SemanticTokenType::new("heading"),
+        SemanticTokenType::new("delimiter"),
+    ]);
+
+    let legend = negotiate_semantic_token_legend(&capabilities);
+
+    assert_eq!(
+        legend.token_types[index(TokenType::Heading)],
+        SemanticTokenType::new("heading")
+    );
+    assert_eq!(
+        legend.token_types[index(TokenType::Delimiter)],
+        SemanticTokenType::new("delimiter")
+    );
+}
+
+#[test]
+fn legend_without_client_token_types_has_no_fallbacks() {
+    let legend = negotiate_semantic_token_legend(&ClientCapabilities::default());
+
+    assert_eq!(legend, create_semantic_token_legend());
+}
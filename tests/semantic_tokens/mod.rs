@@ -1,4 +1,7 @@
 mod block_tokens;
+mod delta;
 mod inline_tokens;
+mod legend;
 mod multiline_tokens;
 mod multiple_blocks;
+mod range_tokens;
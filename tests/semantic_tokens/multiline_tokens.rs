@@ -1,13 +1,70 @@
 use insta::assert_snapshot;
 use unimarkup_core::config::Config;
-use unimarkup_lsp::semantic_tokens::get_semantic_tokens;
+use unimarkup_lsp::line_index::{LineIndex, PositionEncoding};
+use unimarkup_lsp::semantic_tokens::{get_semantic_tokens, SemanticTokensSupport};
 
 #[test]
 fn bold_token_modifier_over_two_lines() {
     let input = "**bold\ntext**";
     let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
 
-    let tokens = get_semantic_tokens(&document);
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
 
     assert_snapshot!(format!("{:#?}", tokens));
 }
+
+#[test]
+fn bold_token_modifier_over_two_lines_with_multiline_support() {
+    let input = "**bold\ntext**";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+    let support = SemanticTokensSupport {
+        multiline: true,
+        ..Default::default()
+    };
+
+    let tokens = get_semantic_tokens(&document, &line_index, support);
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn bold_token_modifier_over_two_lines_with_crlf() {
+    let lf_input = "**bold\ntext**";
+    let crlf_input = "**bold\r\ntext**";
+    let lf_document = unimarkup_core::unimarkup::compile(lf_input, Config::default()).unwrap();
+    let crlf_document = unimarkup_core::unimarkup::compile(crlf_input, Config::default()).unwrap();
+    let lf_line_index = LineIndex::new(lf_input, PositionEncoding::Utf16);
+    let crlf_line_index = LineIndex::new(crlf_input, PositionEncoding::Utf16);
+
+    let lf_tokens = get_semantic_tokens(
+        &lf_document,
+        &lf_line_index,
+        SemanticTokensSupport::default(),
+    );
+    let crlf_tokens = get_semantic_tokens(
+        &crlf_document,
+        &crlf_line_index,
+        SemanticTokensSupport::default(),
+    );
+
+    // The longer line break must not shift the tokens of the next line
+    assert_eq!(crlf_tokens, lf_tokens);
+}
+
+#[test]
+fn multiline_token_length_is_encoded_per_line() {
+    let input = "**bold\n🎉 text**";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+    let support = SemanticTokensSupport {
+        multiline: true,
+        ..Default::default()
+    };
+
+    let tokens = get_semantic_tokens(&document, &line_index, support);
+
+    // "bold\n🎉 text" has 11 characters, but the emoji takes two UTF-16 code units
+    assert_eq!(tokens[1].length, 12);
+}
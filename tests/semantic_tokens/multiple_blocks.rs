@@ -1,13 +1,15 @@
 use insta::assert_snapshot;
 use unimarkup_core::config::Config;
-use unimarkup_lsp::semantic_tokens::get_semantic_tokens;
+use unimarkup_lsp::line_index::{LineIndex, PositionEncoding};
+use unimarkup_lsp::semantic_tokens::{get_semantic_tokens, SemanticTokensSupport};
 
 #[test]
 fn bold_token_modifier_in_two_blocks() {
     let input = "**bold text**\n\n**other bold text**";
     let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
 
-    let tokens = get_semantic_tokens(&document);
+    let tokens = get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default());
 
     assert_snapshot!(format!("{:#?}", tokens));
 }
@@ -0,0 +1,65 @@
+use insta::assert_snapshot;
+use lsp_types::{Position, Range};
+use unimarkup_core::config::Config;
+use unimarkup_lsp::line_index::{LineIndex, PositionEncoding};
+use unimarkup_lsp::semantic_tokens::{
+    get_semantic_tokens, get_semantic_tokens_in_range, SemanticTokensSupport,
+};
+
+#[test]
+fn range_tokens_of_second_block() {
+    let input = "**bold text**\n\n**other bold text**";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+    let range = Range::new(Position::new(2, 0), Position::new(2, 19));
+
+    let tokens = get_semantic_tokens_in_range(
+        &document,
+        &line_index,
+        range,
+        SemanticTokensSupport::default(),
+    );
+
+    assert_snapshot!(format!("{:#?}", tokens));
+}
+
+#[test]
+fn range_over_whole_document_has_all_tokens() {
+    let input = "**bold text**\n\n**other bold text**";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+    let range = Range::new(Position::new(0, 0), Position::new(2, 19));
+
+    let range_tokens = get_semantic_tokens_in_range(
+        &document,
+        &line_index,
+        range,
+        SemanticTokensSupport::default(),
+    );
+
+    assert_eq!(
+        range_tokens,
+        get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default())
+    );
+}
+
+#[test]
+fn range_tokens_of_heading_continuation_line() {
+    let input = "# first\n  **second** line";
+    let document = unimarkup_core::unimarkup::compile(input, Config::default()).unwrap();
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+    let range = Range::new(Position::new(1, 0), Position::new(1, 16));
+
+    let range_tokens = get_semantic_tokens_in_range(
+        &document,
+        &line_index,
+        range,
+        SemanticTokensSupport::default(),
+    );
+
+    // Tokens of the whole heading are returned, although the range only covers its second line
+    assert_eq!(
+        range_tokens,
+        get_semantic_tokens(&document, &line_index, SemanticTokensSupport::default())
+    );
+}
@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde_json::json;
+use unimarkup_lsp::settings::{Settings, DEFAULT_COMPILE_DEBOUNCE};
+
+#[test]
+fn settings_from_section() {
+    let value = json!({ "unimarkup": { "theme": "dark.css", "compileDebounceMs": 300 } });
+
+    let settings = Settings::from_value(&value).unwrap();
+
+    assert_eq!(settings.theme, Some(PathBuf::from("dark.css")));
+    assert_eq!(settings.compile_debounce(), Duration::from_millis(300));
+}
+
+#[test]
+fn settings_without_section() {
+    let value = json!({ "flags": ["draft"] });
+
+    let settings = Settings::from_value(&value).unwrap();
+
+    assert_eq!(settings.flags, Some(vec!["draft".to_string()]));
+    assert_eq!(settings.compile_debounce(), DEFAULT_COMPILE_DEBOUNCE);
+}
+
+#[test]
+fn null_settings_are_ignored() {
+    assert_eq!(Settings::from_value(&serde_json::Value::Null), None);
+}
+
+#[test]
+fn settings_overwrite_config() {
+    let settings = Settings {
+        theme: Some(PathBuf::from("dark.css")),
+        html_embed_svg: Some(true),
+        ..Default::default()
+    };
+
+    let config = settings.to_config();
+
+    assert_eq!(config.theme, Some(PathBuf::from("dark.css")));
+    assert!(config.html_embed_svg);
+    assert!(config.out_formats.is_some());
+}
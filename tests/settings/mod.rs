@@ -0,0 +1 @@
+mod client_settings;
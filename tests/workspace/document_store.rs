@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use lsp_types::Url;
+use unimarkup_core::config::Config;
+use unimarkup_lsp::doc_sync::{remove_indexed_document, store_document, VersionedDocument};
+use unimarkup_lsp::line_index::{LineIndex, PositionEncoding};
+
+fn doc_uri() -> Url {
+    Url::parse("file:///workspace/document.um").unwrap()
+}
+
+fn compiled_doc(version: Option<i32>, text: &str) -> VersionedDocument {
+    VersionedDocument {
+        uri: doc_uri(),
+        version,
+        doc: unimarkup_core::unimarkup::compile(text, Config::default()).unwrap(),
+        line_index: LineIndex::new(text, PositionEncoding::Utf16),
+    }
+}
+
+fn stored_version(documents: &HashMap<Url, VersionedDocument>) -> Option<i32> {
+    documents.get(&doc_uri()).unwrap().version
+}
+
+#[test]
+fn opened_document_replaces_indexed_document() {
+    let mut documents = HashMap::new();
+
+    assert!(store_document(
+        &mut documents,
+        compiled_doc(None, "# On disk")
+    ));
+    assert!(store_document(
+        &mut documents,
+        compiled_doc(Some(1), "# Opened")
+    ));
+
+    assert_eq!(stored_version(&documents), Some(1));
+}
+
+#[test]
+fn indexed_document_does_not_replace_opened_document() {
+    let mut documents = HashMap::new();
+
+    assert!(store_document(
+        &mut documents,
+        compiled_doc(Some(1), "# Opened")
+    ));
+    assert!(!store_document(
+        &mut documents,
+        compiled_doc(None, "# On disk")
+    ));
+
+    assert_eq!(stored_version(&documents), Some(1));
+}
+
+#[test]
+fn older_version_does_not_replace_newer_version() {
+    let mut documents = HashMap::new();
+
+    assert!(store_document(
+        &mut documents,
+        compiled_doc(Some(3), "# Newer")
+    ));
+    assert!(!store_document(
+        &mut documents,
+        compiled_doc(Some(2), "# Older")
+    ));
+
+    assert_eq!(stored_version(&documents), Some(3));
+}
+
+#[test]
+fn closed_document_falls_back_to_indexed_document() {
+    let mut documents = HashMap::new();
+
+    assert!(store_document(
+        &mut documents,
+        compiled_doc(Some(1), "# Opened")
+    ));
+
+    // Closing removes the opened document, before its content on disk is indexed again
+    documents.remove(&doc_uri());
+    assert!(store_document(
+        &mut documents,
+        compiled_doc(None, "# On disk")
+    ));
+
+    assert_eq!(stored_version(&documents), None);
+}
+
+#[test]
+fn failed_indexed_document_is_removed() {
+    let mut documents = HashMap::new();
+
+    assert!(store_document(
+        &mut documents,
+        compiled_doc(None, "# On disk")
+    ));
+
+    assert!(remove_indexed_document(&mut documents, &doc_uri()));
+    assert!(documents.is_empty());
+}
+
+#[test]
+fn opened_document_is_not_removed_with_indexed_document() {
+    let mut documents = HashMap::new();
+
+    assert!(store_document(
+        &mut documents,
+        compiled_doc(Some(1), "# Opened")
+    ));
+
+    assert!(!remove_indexed_document(&mut documents, &doc_uri()));
+    assert_eq!(stored_version(&documents), Some(1));
+}
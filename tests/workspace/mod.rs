@@ -0,0 +1,2 @@
+mod document_store;
+mod um_files;
@@ -0,0 +1,31 @@
+use std::fs;
+
+use unimarkup_lsp::workspace::find_um_files;
+
+#[test]
+fn um_files_are_found_recursively() {
+    let workspace_dir =
+        std::env::temp_dir().join(format!("um-lsp-workspace-{}", std::process::id()));
+    let docs_dir = workspace_dir.join("docs");
+    let hidden_dir = workspace_dir.join(".git");
+    let target_dir = workspace_dir.join("target");
+    fs::create_dir_all(&docs_dir).unwrap();
+    fs::create_dir_all(&hidden_dir).unwrap();
+    fs::create_dir_all(&target_dir).unwrap();
+
+    fs::write(workspace_dir.join("index.um"), "# Index").unwrap();
+    fs::write(docs_dir.join("guide.um"), "# Guide").unwrap();
+    fs::write(docs_dir.join("notes.md"), "# Notes").unwrap();
+    fs::write(hidden_dir.join("hidden.um"), "# Hidden").unwrap();
+    fs::write(target_dir.join("generated.um"), "# Generated").unwrap();
+
+    let mut um_files = find_um_files(&workspace_dir);
+    um_files.sort();
+
+    fs::remove_dir_all(&workspace_dir).unwrap();
+
+    assert_eq!(
+        um_files,
+        vec![docs_dir.join("guide.um"), workspace_dir.join("index.um")]
+    );
+}
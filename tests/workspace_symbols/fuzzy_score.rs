@@ -0,0 +1,43 @@
+use unimarkup_lsp::workspace_symbols::fuzzy_score;
+
+#[test]
+fn characters_must_match_in_order() {
+    assert!(fuzzy_score("ins", "Installation").is_some());
+    assert!(fuzzy_score("itl", "Installation").is_some());
+    assert!(fuzzy_score("sni", "Installation").is_none());
+}
+
+#[test]
+fn matching_ignores_case_and_query_whitespace() {
+    assert!(fuzzy_score("GETTING started", "Getting Started").is_some());
+    assert!(fuzzy_score("gs", "getting-started").is_some());
+}
+
+#[test]
+fn empty_query_matches_everything() {
+    assert_eq!(fuzzy_score("", "Installation"), Some(0));
+}
+
+#[test]
+fn prefix_match_scores_higher_than_inner_match() {
+    let prefix = fuzzy_score("set", "Setup").unwrap();
+    let inner = fuzzy_score("set", "Reset").unwrap();
+
+    assert!(prefix > inner);
+}
+
+#[test]
+fn word_start_match_scores_higher_than_scattered_match() {
+    let word_starts = fuzzy_score("gs", "Getting Started").unwrap();
+    let scattered = fuzzy_score("gs", "Gadgets").unwrap();
+
+    assert!(word_starts > scattered);
+}
+
+#[test]
+fn consecutive_match_scores_higher_than_gaps() {
+    let consecutive = fuzzy_score("inst", "Install").unwrap();
+    let gaps = fuzzy_score("inst", "Introduction to Setup").unwrap();
+
+    assert!(consecutive > gaps);
+}
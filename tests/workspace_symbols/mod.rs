@@ -0,0 +1,2 @@
+mod fuzzy_score;
+mod search;
@@ -0,0 +1,66 @@
+use lsp_types::{OneOf, Position, Range, SymbolKind, Url};
+use unimarkup_core::config::Config;
+use unimarkup_core::document::Document;
+use unimarkup_lsp::line_index::{LineIndex, PositionEncoding};
+use unimarkup_lsp::workspace_symbols::{find_workspace_symbols, resolve_workspace_symbol};
+
+fn compile(input: &str) -> Document {
+    unimarkup_core::unimarkup::compile(input, Config::default()).unwrap()
+}
+
+#[test]
+fn headings_of_all_documents_are_found() {
+    let guide_uri = Url::parse("file:///docs/guide.um").unwrap();
+    let guide = compile("# Installation\n\ntext\n\n# Usage");
+    let faq_uri = Url::parse("file:///docs/faq.um").unwrap();
+    let faq = compile("# Install problems");
+
+    let symbols = find_workspace_symbols("inst", [(&guide_uri, &guide), (&faq_uri, &faq)]);
+
+    let names: Vec<_> = symbols.iter().map(|symbol| symbol.name.as_str()).collect();
+    assert_eq!(names, ["Installation", "Install problems"]);
+    assert_eq!(symbols[0].kind, SymbolKind::STRING);
+    assert_eq!(symbols[0].container_name.as_deref(), Some("guide.um"));
+    assert_eq!(symbols[1].container_name.as_deref(), Some("faq.um"));
+}
+
+#[test]
+fn closer_matches_are_ranked_first() {
+    let uri = Url::parse("file:///docs/guide.um").unwrap();
+    let document = compile("# Reset settings\n\n# Setup");
+
+    let symbols = find_workspace_symbols("set", [(&uri, &document)]);
+
+    let names: Vec<_> = symbols.iter().map(|symbol| symbol.name.as_str()).collect();
+    assert_eq!(names, ["Setup", "Reset settings"]);
+}
+
+#[test]
+fn symbols_are_resolved_lazily() {
+    let input = "text\n\n# Usage";
+    let uri = Url::parse("file:///docs/guide.um").unwrap();
+    let document = compile(input);
+    let line_index = LineIndex::new(input, PositionEncoding::Utf16);
+
+    let symbol = find_workspace_symbols("usage", [(&uri, &document)]).remove(0);
+    assert!(matches!(&symbol.location, OneOf::Right(location) if location.uri == uri));
+
+    let symbol = resolve_workspace_symbol(symbol, &document, &line_index);
+
+    let OneOf::Left(location) = symbol.location else {
+        panic!("Symbol location has no range.");
+    };
+    assert_eq!(location.uri, uri);
+    assert_eq!(
+        location.range,
+        Range::new(Position::new(2, 0), Position::new(2, 7))
+    );
+}
+
+#[test]
+fn unmatched_query_finds_nothing() {
+    let uri = Url::parse("file:///docs/guide.um").unwrap();
+    let document = compile("# Installation");
+
+    assert!(find_workspace_symbols("xyz", [(&uri, &document)]).is_empty());
+}